@@ -0,0 +1,263 @@
+use std::env;
+
+use crate::solution::Part;
+
+pub const USAGE: &str = "usage:
+    advent-2020 run <day> [--part 1|2] [--input path|-]
+    advent-2020 audit [--policy name[:argument]]... [--input path|-] [--report path|-]
+    advent-2020 trace <right> <down> [--topology name] [--input path|-]
+    advent-2020 passports [--presence] [--format table|csv|json] [--birth-bucket years] [--input path|-]
+    advent-2020 normalize [--drop-invalid] [--input path|-]";
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Run {
+        day: u8,
+        part: Option<Part>,
+        input: Option<String>,
+    },
+    Audit {
+        policies: Vec<String>,
+        input: Option<String>,
+        report: Option<String>,
+    },
+    Trace {
+        right: usize,
+        down: usize,
+        topology: Option<String>,
+        input: Option<String>,
+    },
+    Passports {
+        presence: bool,
+        format: Option<String>,
+        birth_bucket: Option<u16>,
+        input: Option<String>,
+    },
+    Normalize {
+        drop_invalid: bool,
+        input: Option<String>,
+    },
+}
+
+impl Command {
+    pub fn from_env() -> Result<Command, String> {
+        Self::from_args(env::args().skip(1).collect())
+    }
+
+    pub fn from_args(args: Vec<String>) -> Result<Command, String> {
+        let mut args = args.into_iter();
+
+        match args.next().as_deref() {
+            Some("run") => parse_run(args),
+            Some("audit") => parse_audit(args),
+            Some("trace") => parse_trace(args),
+            Some("passports") => parse_passports(args),
+            Some("normalize") => parse_normalize(args),
+            Some(other) => Err(format!("unknown command `{}`", other)),
+            None => Err("missing command".to_string()),
+        }
+    }
+}
+
+fn parse_run(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let day = args
+        .next()
+        .ok_or("missing day")?
+        .parse::<u8>()
+        .map_err(|_| "day must be a number")?;
+
+    let mut part = None;
+    let mut input = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--part" => {
+                let value = args.next().ok_or("--part needs a value")?;
+                part = match value.as_str() {
+                    "1" => Some(Part::One),
+                    "2" => Some(Part::Two),
+                    _ => return Err(format!("invalid part `{}`, expected 1 or 2", value)),
+                };
+            }
+            "--input" => {
+                input = Some(args.next().ok_or("--input needs a path")?);
+            }
+            other => return Err(format!("unexpected argument `{}`", other)),
+        }
+    }
+
+    Ok(Command::Run { day, part, input })
+}
+
+fn parse_audit(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut policies = vec![];
+    let mut input = None;
+    let mut report = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--policy" => policies.push(args.next().ok_or("--policy needs a name")?),
+            "--input" => input = Some(args.next().ok_or("--input needs a path")?),
+            "--report" => report = Some(args.next().ok_or("--report needs a path")?),
+            other => return Err(format!("unexpected argument `{}`", other)),
+        }
+    }
+
+    Ok(Command::Audit { policies, input, report })
+}
+
+fn parse_trace(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut step = |name: &str| -> Result<usize, String> {
+        args.next()
+            .ok_or(format!("missing {}", name))?
+            .parse::<usize>()
+            .map_err(|_| format!("{} must be a number", name))
+    };
+
+    let right = step("right")?;
+    let down = step("down")?;
+    let mut topology = None;
+    let mut input = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--topology" => topology = Some(args.next().ok_or("--topology needs a name")?),
+            "--input" => input = Some(args.next().ok_or("--input needs a path")?),
+            other => return Err(format!("unexpected argument `{}`", other)),
+        }
+    }
+
+    Ok(Command::Trace { right, down, topology, input })
+}
+
+fn parse_passports(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut presence = false;
+    let mut format = None;
+    let mut birth_bucket = None;
+    let mut input = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--presence" => presence = true,
+            "--format" => format = Some(args.next().ok_or("--format needs a name")?),
+            "--birth-bucket" => {
+                let years = args.next().ok_or("--birth-bucket needs a number of years")?;
+                birth_bucket = Some(years.parse::<u16>().map_err(|_| "--birth-bucket must be a number".to_string())?);
+            }
+            "--input" => input = Some(args.next().ok_or("--input needs a path")?),
+            other => return Err(format!("unexpected argument `{}`", other)),
+        }
+    }
+
+    Ok(Command::Passports { presence, format, birth_bucket, input })
+}
+
+fn parse_normalize(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut drop_invalid = false;
+    let mut input = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--drop-invalid" => drop_invalid = true,
+            "--input" => input = Some(args.next().ok_or("--input needs a path")?),
+            other => return Err(format!("unexpected argument `{}`", other)),
+        }
+    }
+
+    Ok(Command::Normalize { drop_invalid, input })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_run() {
+        assert_eq!(
+            Command::from_args(args("run 3")),
+            Ok(Command::Run { day: 3, part: None, input: None })
+        );
+
+        assert_eq!(
+            Command::from_args(args("run 7 --part 2 --input foo.txt")),
+            Ok(Command::Run { day: 7, part: Some(Part::Two), input: Some("foo.txt".to_string()) })
+        );
+    }
+
+    #[test]
+    fn test_parse_audit() {
+        assert_eq!(
+            Command::from_args(args("audit --policy occurrence --policy min-distinct:3 --input - --report out.jsonl")),
+            Ok(Command::Audit {
+                policies: vec!["occurrence".to_string(), "min-distinct:3".to_string()],
+                input: Some("-".to_string()),
+                report: Some("out.jsonl".to_string()),
+            })
+        );
+
+        assert!(Command::from_args(args("audit --policy")).is_err());
+    }
+
+    #[test]
+    fn test_parse_trace() {
+        assert_eq!(
+            Command::from_args(args("trace 3 1")),
+            Ok(Command::Trace { right: 3, down: 1, topology: None, input: None })
+        );
+
+        assert_eq!(
+            Command::from_args(args("trace 1 2 --topology torus --input -")),
+            Ok(Command::Trace { right: 1, down: 2, topology: Some("torus".to_string()), input: Some("-".to_string()) })
+        );
+
+        assert_eq!(Command::from_args(args("trace 3")), Err("missing down".to_string()));
+        assert_eq!(Command::from_args(args("trace x 1")), Err("right must be a number".to_string()));
+    }
+
+    #[test]
+    fn test_parse_passports() {
+        assert_eq!(
+            Command::from_args(args("passports")),
+            Ok(Command::Passports { presence: false, format: None, birth_bucket: None, input: None })
+        );
+
+        assert_eq!(
+            Command::from_args(args("passports --format csv --presence --birth-bucket 1 --input -")),
+            Ok(Command::Passports {
+                presence: true,
+                format: Some("csv".to_string()),
+                birth_bucket: Some(1),
+                input: Some("-".to_string()),
+            })
+        );
+
+        assert!(Command::from_args(args("passports --format")).is_err());
+        assert_eq!(
+            Command::from_args(args("passports --birth-bucket decade")),
+            Err("--birth-bucket must be a number".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_normalize() {
+        assert_eq!(
+            Command::from_args(args("normalize --drop-invalid --input batch.txt")),
+            Ok(Command::Normalize { drop_invalid: true, input: Some("batch.txt".to_string()) })
+        );
+        assert!(Command::from_args(args("normalize --strict")).is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Command::from_args(args("")).is_err());
+        assert!(Command::from_args(args("walk 3")).is_err());
+        assert!(Command::from_args(args("run")).is_err());
+        assert!(Command::from_args(args("run three")).is_err());
+        assert!(Command::from_args(args("run 3 --part 3")).is_err());
+        assert!(Command::from_args(args("run 3 --input")).is_err());
+    }
+}
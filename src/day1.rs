@@ -1,30 +1,183 @@
 #![allow(dead_code)]
 
-fn find_complements_for_num(nums: &Vec<usize>, target: usize) -> Option<(usize, usize)> {
-    nums.iter().find_map(|&x| {
-        nums.iter()
-            .find(|&&y| target > x && y == target - x)
-            .map(|&y| (y, x))
-    })
+use std::cmp::Ordering;
+
+use crate::error::{Error, Result};
+use crate::input::{read_nums, Mode};
+use crate::solution::Solution;
+
+pub struct Day1;
+
+/// Finds `k` distinct entries of `nums` that add up to `target`, returned in
+/// ascending order. Works on a sorted copy: the last two entries are found
+/// with two pointers, every entry before that fixes one more number.
+pub fn k_sum(nums: &[usize], target: usize, k: usize) -> Option<Vec<usize>> {
+    let mut sorted = nums.to_vec();
+    sorted.sort_unstable();
+
+    sorted_k_sum(&sorted, target, k)
+}
+
+fn sorted_k_sum(nums: &[usize], target: usize, k: usize) -> Option<Vec<usize>> {
+    match k {
+        0 => if target == 0 { Some(vec![]) } else { None },
+        1 => nums.binary_search(&target).ok().map(|_| vec![target]),
+        2 => two_sum(nums, target),
+        _ => {
+            for (i, &x) in nums.iter().enumerate() {
+                if x > target {
+                    break;
+                }
+
+                if i > 0 && nums[i - 1] == x {
+                    continue;
+                }
+
+                if let Some(mut rest) = sorted_k_sum(&nums[i + 1..], target - x, k - 1) {
+                    rest.insert(0, x);
+                    return Some(rest);
+                }
+            }
+
+            None
+        }
+    }
 }
 
-fn product_of_pairs(nums: Vec<usize>) -> Option<usize> {
-    nums.iter().find_map(|x| {
-        find_complements_for_num(&nums, 2020 - x)
-            .map(|(y, z)| x * y * z)
-    })
+fn two_sum(nums: &[usize], target: usize) -> Option<Vec<usize>> {
+    if nums.len() < 2 {
+        return None;
+    }
+
+    let (mut lo, mut hi) = (0, nums.len() - 1);
+
+    while lo < hi {
+        match nums[lo].checked_add(nums[hi]).map(|sum| sum.cmp(&target)) {
+            Some(Ordering::Equal) => return Some(vec![nums[lo], nums[hi]]),
+            Some(Ordering::Less) => lo += 1,
+            _ => hi -= 1,
+        }
+    }
 
+    None
+}
+
+fn product_of_entries(nums: &[usize], k: usize) -> Option<usize> {
+    k_sum(nums, 2020, k).map(|entries| entries.iter().product())
+}
+
+impl Solution for Day1 {
+    const DAY: u8 = 1;
+
+    type Input = Vec<usize>;
+    type Answer = usize;
+
+    fn parse(input: &str) -> Result<Vec<usize>> {
+        read_nums(input.as_bytes(), Mode::Strict)
+    }
+
+    fn part_one(nums: &Vec<usize>) -> Result<usize> {
+        product_of_entries(nums, 2).ok_or_else(|| Error::validation("no two entries sum to 2020"))
+    }
+
+    fn part_two(nums: &Vec<usize>) -> Result<usize> {
+        product_of_entries(nums, 3).ok_or_else(|| Error::validation("no three entries sum to 2020"))
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::input::{read_nums};
+    use crate::input::{open, read_file};
     use super::*;
 
+    use proptest::prelude::*;
+
+    fn brute_force(nums: &[usize], target: usize, k: usize) -> bool {
+        match (k, nums.split_first()) {
+            (0, _) => target == 0,
+            (_, None) => false,
+            (_, Some((&x, rest))) => {
+                (x <= target && brute_force(rest, target - x, k - 1)) || brute_force(rest, target, k)
+            }
+        }
+    }
+
+    fn is_sub_multiset(entries: &[usize], nums: &[usize]) -> bool {
+        let mut nums = nums.to_vec();
+
+        entries.iter().all(|e| match nums.iter().position(|n| n == e) {
+            Some(i) => { nums.swap_remove(i); true }
+            None => false,
+        })
+    }
+
+    #[test]
+    fn test_k_sum() {
+        let nums = vec![1721, 979, 366, 299, 675, 1456];
+
+        assert_eq!(k_sum(&nums, 2020, 2), Some(vec![299, 1721]));
+        assert_eq!(k_sum(&nums, 2020, 3), Some(vec![366, 675, 979]));
+        assert_eq!(k_sum(&nums, 2020, 4), None);
+        assert_eq!(k_sum(&nums, 0, 0), Some(vec![]));
+        assert_eq!(k_sum(&[], 2020, 2), None);
+        assert_eq!(k_sum(&[usize::MAX, 1, 2], 3, 2), Some(vec![1, 2]));
+    }
+
+    proptest! {
+        #[test]
+        fn k_sum_matches_brute_force(
+            nums in prop::collection::vec(0usize..100, 0..12),
+            target in 0usize..300,
+            k in 2usize..=5,
+        ) {
+            let found = k_sum(&nums, target, k);
+
+            prop_assert_eq!(found.is_some(), brute_force(&nums, target, k));
+
+            if let Some(entries) = found {
+                prop_assert_eq!(entries.len(), k);
+                prop_assert_eq!(entries.iter().sum::<usize>(), target);
+                prop_assert!(is_sub_multiset(&entries, &nums));
+            }
+        }
+    }
+
+    #[test]
+    fn test_entries_are_used_once() {
+        assert_eq!(product_of_entries(&[1010, 5, 7], 2), None);
+        assert_eq!(product_of_entries(&[1010, 5, 1010], 2), Some(1010 * 1010));
+        assert_eq!(Day1::part_one(&vec![1010]), Err(Error::validation("no two entries sum to 2020")));
+
+        assert_eq!(product_of_entries(&[1000, 10, 20], 3), None);
+        assert_eq!(product_of_entries(&[1000, 10, 1000, 20], 3), Some(1000 * 1000 * 20));
+    }
+
+    #[test]
+    fn test_part_one() {
+        let nums = Day1::parse(&read_file("input/day1.txt").unwrap()).unwrap();
+
+        assert_eq!(Day1::part_one(&nums), Ok(1018944));
+    }
+
+    #[test]
+    fn test_part_two() {
+        let nums = Day1::parse(&read_file("input/day1.txt").unwrap()).unwrap();
+
+        assert_eq!(Day1::part_two(&nums), Ok(8446464));
+    }
+
     #[test]
     fn test_product() {
-        let nums = read_nums("input/day1.txt");
+        let nums = read_nums(open("input/day1.txt").unwrap(), Mode::Strict).unwrap();
 
-        assert_eq!(product_of_pairs(nums), Some(8446464));
+        assert_eq!(product_of_entries(&nums, 3), Some(8446464));
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert_eq!(
+            Day1::parse("1721\n97x\n"),
+            Err(Error::Parse { line: Some(2), column: None, field: None, reason: "invalid number `97x`: invalid digit found in string".to_string() })
+        );
     }
 }
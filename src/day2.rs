@@ -1,83 +1,127 @@
 #![allow(dead_code)]
 
-use regex::Regex;
+use std::str::FromStr;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::error::{Error, Result};
+use crate::solution::Solution;
+
+pub mod audit;
+pub mod policy;
+
+use policy::{Occurrence, PasswordPolicy, Position, Unit};
+
+pub struct Day2;
 
 #[derive(Debug, PartialEq)]
-struct Password {
+pub struct Password {
     parameters: (usize, usize),
-    symbol: char,
+    symbol: String,
     password: String,
 }
 
 impl Password {
-    fn new(symbol: char, password: &str, parameters: (usize, usize)) -> Self {
-        Password { parameters, symbol, password: password.to_owned() }
+    fn new(symbol: &str, password: &str, parameters: (usize, usize)) -> Self {
+        Password { parameters, symbol: symbol.to_owned(), password: password.to_owned() }
     }
+}
 
-    fn from_string(input: &str) -> Self {
-        let re = Regex::new(r"(\d+)-(\d+)\s([a-z]):\s([a-z]+)").unwrap();
-        let matches = re.captures(input).unwrap();
-
-        let parameters = (
-            matches.get(1).unwrap().as_str().parse().unwrap(),
-            matches.get(2).unwrap().as_str().parse().unwrap()
-        );
+fn parse_field<T: FromStr>(value: &str, field: &str, column: usize) -> Result<T> {
+    value
+        .parse()
+        .map_err(|_| Error::parse(format!("`{}` is not a number", value)).in_field(field).at_column(column))
+}
 
-        Self::new(
-            matches.get(3).unwrap().as_str().chars().next().unwrap(),
-            matches.get(4).unwrap().as_str(),
-            parameters,
-        )
-    }
+fn is_valid_symbol(symbol: &str) -> bool {
+    !symbol.is_empty()
+        && !symbol.chars().any(char::is_whitespace)
+        && symbol.graphemes(true).nth(1).is_none()
 }
 
-fn parse_input(lines: Vec<String>) -> Vec<Password> {
-    lines.iter().map(|line| Password::from_string(line)).collect()
+fn is_valid_password(password: &str) -> bool {
+    !password.is_empty() && !password.chars().any(char::is_whitespace)
 }
 
-fn verify_password_by_occurance(p: &Password) -> bool {
-    let occurances = p.password.chars().fold(0, |acc, c| {
-        if c == p.symbol {
-            acc + 1
-        } else {
-            acc
+impl FromStr for Password {
+    type Err = Error;
+
+    /// Parses `<min>-<max> <symbol>: <password>`, where the symbol is a single
+    /// grapheme and the password anything without whitespace. Errors name the
+    /// field and column they occurred in, columns count chars rather than
+    /// bytes.
+    fn from_str(input: &str) -> Result<Password> {
+        let (policy, password) = input
+            .split_once(": ")
+            .ok_or_else(|| Error::parse(format!("expected `<min>-<max> <symbol>: <password>`, got `{}`", input)))?;
+
+        let (range, symbol) = policy
+            .split_once(' ')
+            .ok_or_else(|| Error::parse("missing symbol").in_field("symbol").at_column(policy.chars().count() + 1))?;
+
+        let (min, max) = range
+            .split_once('-')
+            .ok_or_else(|| Error::parse(format!("expected `<min>-<max>`, got `{}`", range)).in_field("max").at_column(range.chars().count() + 1))?;
+
+        let max_column = min.chars().count() + 2;
+        let min = parse_field(min, "min", 1)?;
+        let max = parse_field(max, "max", max_column)?;
+
+        if !is_valid_symbol(symbol) {
+            let column = range.chars().count() + 2;
+            return Err(Error::parse(format!("invalid symbol `{}`", symbol)).in_field("symbol").at_column(column));
         }
-    });
 
-    let (min, max) = p.parameters;
+        let password_column = input[..input.len() - password.trim_start().len()].chars().count() + 1;
+        let password = password.trim();
+        if !is_valid_password(password) {
+            return Err(Error::parse(format!("invalid password `{}`", password)).in_field("password").at_column(password_column));
+        }
 
-    occurances >= min && occurances <= max
+        Ok(Password::new(symbol, password, (min, max)))
+    }
 }
 
-fn verify_password_by_position(p: &Password) -> bool {
-    let (a, b) = p.parameters;
-
-    let char_a = p.password.chars().collect::<Vec<_>>().get(a - 1).unwrap().to_owned();
-    let char_b = p.password.chars().collect::<Vec<_>>().get(b - 1).unwrap().to_owned();
-
-    (char_a == p.symbol) ^ (char_b == p.symbol)
+fn parse_input<S: AsRef<str>>(lines: &[S]) -> Result<Vec<Password>> {
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !line.as_ref().trim().is_empty())
+        .map(|(i, line)| line.as_ref().trim().parse().map_err(|e: Error| e.at_line(i + 1)))
+        .collect()
 }
 
-fn part_one(lines: Vec<String>) -> usize {
-    parse_input(lines)
-        .iter()
-        .map(|p| verify_password_by_occurance(p))
-        .filter(|&b| b)
-        .count()
+fn verify_password_by_occurance(p: &Password) -> bool {
+    Occurrence(Unit::Char).check(p).is_ok()
 }
 
-fn part_two(lines: Vec<String>) -> usize {
-    parse_input(lines)
-        .iter()
-        .map(|p| verify_password_by_position(p))
-        .filter(|&b| b)
-        .count()
+fn verify_password_by_position(p: &Password) -> bool {
+    Position(Unit::Char).check(p).is_ok()
 }
 
+impl Solution for Day2 {
+    const DAY: u8 = 2;
+
+    type Input = Vec<Password>;
+    type Answer = usize;
+
+    fn parse(input: &str) -> Result<Vec<Password>> {
+        let lines: Vec<&str> = input.lines().collect();
+        parse_input(&lines)
+    }
+
+    fn part_one(passwords: &Vec<Password>) -> Result<usize> {
+        Ok(passwords.iter().filter(|p| verify_password_by_occurance(p)).count())
+    }
+
+    fn part_two(passwords: &Vec<Password>) -> Result<usize> {
+        Ok(passwords.iter().filter(|p| verify_password_by_position(p)).count())
+    }
+}
 
 #[cfg(test)]
 mod tests {
-    use crate::input::{read_lines};
+    use crate::input::{read_file};
     use super::*;
 
     #[test]
@@ -87,45 +131,87 @@ mod tests {
             "2-9 b: ccddbb".to_string(),
         ];
 
-        assert_eq!(parse_input(input), vec![
-           Password { parameters: (1, 3), symbol: 'a', password: "aaabbbd".to_string() },
-           Password { parameters: (2, 9), symbol: 'b', password: "ccddbb".to_string() },
-        ])
+        assert_eq!(parse_input(&input), Ok(vec![
+           Password { parameters: (1, 3), symbol: "a".to_string(), password: "aaabbbd".to_string() },
+           Password { parameters: (2, 9), symbol: "b".to_string(), password: "ccddbb".to_string() },
+        ]))
+    }
+
+    #[test]
+    fn test_parse_input_errors() {
+        let input = vec![
+            "1-3 a: aaabbbd".to_string(),
+            "2-x b: ccddbb".to_string(),
+        ];
+
+        assert_eq!(parse_input(&input), Err(Error::Parse {
+            line: Some(2),
+            column: Some(3),
+            field: Some("max".to_string()),
+            reason: "`x` is not a number".to_string(),
+        }));
+    }
+
+    #[test]
+    fn test_parse_password_errors() {
+        let error = |s: &str| s.parse::<Password>().unwrap_err().to_string();
+
+        assert_eq!(error("1-3 a abcde"), "parse error: expected `<min>-<max> <symbol>: <password>`, got `1-3 a abcde`");
+        assert_eq!(error("1-3: abcde"), "parse error at column 4 in `symbol`: missing symbol");
+        assert_eq!(error("13 a: abcde"), "parse error at column 3 in `max`: expected `<min>-<max>`, got `13`");
+        assert_eq!(error("x-3 a: abcde"), "parse error at column 1 in `min`: `x` is not a number");
+        assert_eq!(error("1-3 ab: abcde"), "parse error at column 5 in `symbol`: invalid symbol `ab`");
+        assert_eq!(error("1-3 a: "), "parse error at column 8 in `password`: invalid password ``");
+        assert_eq!(error("10-13 a: ab de"), "parse error at column 10 in `password`: invalid password `ab de`");
+        assert_eq!(error("1-3 é: ab cd"), "parse error at column 8 in `password`: invalid password `ab cd`");
+        assert_eq!(error("1-3 éé: abcde"), "parse error at column 5 in `symbol`: invalid symbol `éé`");
+        assert_eq!(error("½-3 a: abcde"), "parse error at column 1 in `min`: `½` is not a number");
+        assert_eq!(error("1-½ a: abcde"), "parse error at column 3 in `max`: `½` is not a number");
+        assert_eq!(error("½3 a: abcde"), "parse error at column 3 in `max`: expected `<min>-<max>`, got `½3`");
+    }
+
+    #[test]
+    fn test_parse_unicode() {
+        assert_eq!("1-3 é: café".parse(), Ok(Password::new("é", "café", (1, 3))));
+        assert_eq!("2-4 !: a!b:c!".parse(), Ok(Password::new("!", "a!b:c!", (2, 4))));
+        assert_eq!("1-1 :: ::".parse(), Ok(Password::new(":", "::", (1, 1))));
+        assert_eq!("1-2 e\u{301}: e\u{301}x".parse(), Ok(Password::new("e\u{301}", "e\u{301}x", (1, 2))));
+        assert_eq!("1-2 7: 1234567".parse(), Ok(Password::new("7", "1234567", (1, 2))));
     }
 
     #[test]
     fn test_verify_password_by_occurance() {
-        let correct_password_1 = Password::new('e', "abcdeefg", (2, 3));
-        let correct_password_2 = Password::new('e', "abcdeeeeeeeefg", (5, 9));
-        let incorrect_password_1 = Password::new('a', "abbabbcc", (3, 5));
-        let incorrect_password_2 = Password::new('a', "bbcaaaaaaaaaaaaabbbcc", (3, 12));
-
-        assert_eq!(verify_password_by_occurance(&correct_password_1), true);
-        assert_eq!(verify_password_by_occurance(&correct_password_2), true);
-        assert_eq!(verify_password_by_occurance(&incorrect_password_1), false);
-        assert_eq!(verify_password_by_occurance(&incorrect_password_2), false);
+        let correct_password_1 = Password::new("e", "abcdeefg", (2, 3));
+        let correct_password_2 = Password::new("e", "abcdeeeeeeeefg", (5, 9));
+        let incorrect_password_1 = Password::new("a", "abbabbcc", (3, 5));
+        let incorrect_password_2 = Password::new("a", "bbcaaaaaaaaaaaaabbbcc", (3, 12));
+
+        assert!(verify_password_by_occurance(&correct_password_1));
+        assert!(verify_password_by_occurance(&correct_password_2));
+        assert!(!verify_password_by_occurance(&incorrect_password_1));
+        assert!(!verify_password_by_occurance(&incorrect_password_2));
     }
 
     #[test]
     fn test_verify_password_by_position() {
-        let correct_password_1 = Password::new('e', "abebbbbb", (1, 3));
-        let correct_password_2 = Password::new('e', "ebabbbbb", (1, 3));
-        let incorrect_password_1 = Password::new('e', "ebebbbbb", (1, 3));
+        let correct_password_1 = Password::new("e", "abebbbbb", (1, 3));
+        let correct_password_2 = Password::new("e", "ebabbbbb", (1, 3));
+        let incorrect_password_1 = Password::new("e", "ebebbbbb", (1, 3));
 
-        assert_eq!(verify_password_by_position(&correct_password_1), true);
-        assert_eq!(verify_password_by_position(&correct_password_2), true);
-        assert_eq!(verify_password_by_position(&incorrect_password_1), false);
+        assert!(verify_password_by_position(&correct_password_1));
+        assert!(verify_password_by_position(&correct_password_2));
+        assert!(!verify_password_by_position(&incorrect_password_1));
     }
 
     #[test]
     fn test_part_one() {
-        let input = read_lines("input/day2.txt");
-        assert_eq!(part_one(input), 538);
+        let input = Day2::parse(&read_file("input/day2.txt").unwrap()).unwrap();
+        assert_eq!(Day2::part_one(&input), Ok(538));
     }
 
     #[test]
     fn test_part_two() {
-        let input = read_lines("input/day2.txt");
-        assert_eq!(part_two(input), 489);
+        let input = Day2::parse(&read_file("input/day2.txt").unwrap()).unwrap();
+        assert_eq!(Day2::part_two(&input), Ok(489));
     }
 }
@@ -0,0 +1,231 @@
+use std::io::BufRead;
+
+use crate::error::{Error, Result};
+use crate::json;
+use super::Password;
+use super::policy::PasswordPolicy;
+
+#[derive(Debug, PartialEq)]
+pub struct Failure {
+    pub policy: String,
+    pub reason: String,
+}
+
+/// A password that failed at least one policy.
+#[derive(Debug, PartialEq)]
+pub struct Invalid<'a> {
+    pub line: usize,
+    pub entry: &'a str,
+    pub failures: &'a [Failure],
+}
+
+impl Invalid<'_> {
+    pub fn to_json(&self) -> String {
+        let failures: Vec<String> = self
+            .failures
+            .iter()
+            .map(|f| json::object(&[("policy", json::string(&f.policy)), ("reason", json::string(&f.reason))]))
+            .collect();
+
+        json::object(&[
+            ("line", self.line.to_string()),
+            ("entry", json::string(self.entry)),
+            ("failures", json::array(&failures)),
+        ])
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Report {
+    pub checked: usize,
+    /// Number of lines that couldn't be read as a password, either because
+    /// they aren't valid UTF-8 or don't parse. They aren't part of `checked`.
+    pub unparsed: usize,
+    /// Number of valid passwords per policy, in the order the policies were given.
+    pub valid: Vec<(String, usize)>,
+}
+
+/// The policy name lines that can't be parsed are reported under.
+pub const PARSE: &str = "parse";
+
+fn reason(error: Error) -> String {
+    match error {
+        Error::Validation(reason) => reason,
+        e => e.to_string(),
+    }
+}
+
+/// Checks every password read from `reader` against all `policies` in a
+/// single pass. Lines are read one at a time into a reused buffer, so memory
+/// use doesn't grow with the input; invalid entries are handed to
+/// `on_invalid` as they are found instead of being collected. Lines that
+/// aren't valid UTF-8 or don't parse are handed over the same way, failing
+/// the `parse` policy, and the audit carries on with the next line.
+pub fn audit<R, F>(mut reader: R, policies: &[Box<dyn PasswordPolicy>], mut on_invalid: F) -> Result<Report>
+where
+    R: BufRead,
+    F: FnMut(&Invalid) -> Result<()>,
+{
+    let mut valid: Vec<(String, usize)> = policies.iter().map(|p| (p.name().to_string(), 0)).collect();
+    let mut checked = 0;
+    let mut failures = vec![];
+    let mut buffer = vec![];
+    let mut line_number = 0;
+    let mut unparsed = 0;
+
+    loop {
+        buffer.clear();
+        if reader.read_until(b'\n', &mut buffer)? == 0 {
+            break;
+        }
+
+        line_number += 1;
+        let entry = String::from_utf8_lossy(&buffer);
+        let line = entry.trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        failures.clear();
+
+        let parsed = match std::str::from_utf8(&buffer) {
+            Ok(_) => line.parse::<Password>(),
+            Err(e) => Err(Error::parse(format!("line is not valid UTF-8 ({})", e))),
+        };
+
+        let password = match parsed {
+            Ok(password) => password,
+            Err(e) => {
+                unparsed += 1;
+                failures.push(Failure { policy: PARSE.to_string(), reason: reason(e) });
+                on_invalid(&Invalid { line: line_number, entry: line, failures: &failures })?;
+                continue;
+            }
+        };
+
+        checked += 1;
+
+        for (policy, (_, count)) in policies.iter().zip(valid.iter_mut()) {
+            match policy.check(&password) {
+                Ok(()) => *count += 1,
+                Err(e) => failures.push(Failure { policy: policy.name().to_string(), reason: reason(e) }),
+            }
+        }
+
+        if !failures.is_empty() {
+            on_invalid(&Invalid { line: line_number, entry: line, failures: &failures })?;
+        }
+    }
+
+    Ok(Report { checked, unparsed, valid })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day2::policy::PolicyRegistry;
+    use crate::input::open;
+
+    fn policies(specs: &[&str]) -> Vec<Box<dyn PasswordPolicy>> {
+        let registry = PolicyRegistry::new();
+        specs.iter().map(|spec| registry.create(spec).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_audit() {
+        let policies = policies(&["occurrence", "position"]);
+        let mut invalid = vec![];
+
+        let report = audit("1-3 a: abcde\n1-3 b: cdefg\n\n2-9 c: ccccccccc\n".as_bytes(), &policies, |i| {
+            invalid.push(i.to_json());
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(report.checked, 3);
+        assert_eq!(report.valid, vec![("occurrence".to_string(), 2), ("position".to_string(), 1)]);
+        assert_eq!(invalid, vec![
+            r#"{"line":2,"entry":"1-3 b: cdefg","failures":[{"policy":"occurrence","reason":"`b` occurs 0 times, expected 1-3"},{"policy":"position","reason":"`b` is at neither position 1 nor 3"}]}"#,
+            r#"{"line":4,"entry":"2-9 c: ccccccccc","failures":[{"policy":"position","reason":"`c` is at both position 2 and 9"}]}"#,
+        ]);
+    }
+
+    #[test]
+    fn test_audit_file() {
+        let policies = policies(&["occurrence", "position"]);
+        let mut invalid = 0;
+
+        let report = audit(open("input/day2.txt").unwrap(), &policies, |_| {
+            invalid += 1;
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(report, Report {
+            checked: 1000,
+            unparsed: 0,
+            valid: vec![("occurrence".to_string(), 538), ("position".to_string(), 489)],
+        });
+        assert!(invalid >= 1000 - 489);
+    }
+
+    #[test]
+    fn test_audit_parse_error() {
+        let policies = policies(&["occurrence"]);
+        let mut invalid = vec![];
+
+        let report = audit("1-3 a: abcde\nnonsense\n1-3 b: bb\n".as_bytes(), &policies, |i| {
+            invalid.push(i.to_json());
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(report, Report { checked: 2, unparsed: 1, valid: vec![("occurrence".to_string(), 2)] });
+        assert_eq!(invalid, vec![
+            r#"{"line":2,"entry":"nonsense","failures":[{"policy":"parse","reason":"parse error: expected `<min>-<max> <symbol>: <password>`, got `nonsense`"}]}"#,
+        ]);
+    }
+
+    #[test]
+    fn test_audit_invalid_utf8() {
+        let policies = policies(&["occurrence"]);
+        let mut invalid = vec![];
+
+        let report = audit(&b"1-3 a: ab\xffde\n1-3 a: abcde\n"[..], &policies, |i| {
+            invalid.push((i.line, i.entry.to_string(), i.failures[0].policy.clone()));
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(report, Report { checked: 1, unparsed: 1, valid: vec![("occurrence".to_string(), 1)] });
+        assert_eq!(invalid, vec![(1, "1-3 a: ab\u{fffd}de".to_string(), "parse".to_string())]);
+    }
+
+    #[test]
+    fn test_audit_stops_on_callback_error() {
+        let policies = policies(&["occurrence"]);
+        let mut seen = 0;
+
+        let result = audit("1-3 b: cdefg\n1-3 b: cdefg\n".as_bytes(), &policies, |_| {
+            seen += 1;
+            Err(Error::validation("report is full"))
+        });
+
+        assert_eq!(result, Err(Error::validation("report is full")));
+        assert_eq!(seen, 1);
+    }
+
+    #[test]
+    fn test_audit_out_of_range_position() {
+        let policies = policies(&["position"]);
+        let mut reasons = vec![];
+
+        let report = audit("1-3 a: abcde\n0-2 a: ab\n1-7 b: bbb\n".as_bytes(), &policies, |i| {
+            reasons.push(i.failures[0].reason.clone());
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(report.valid, vec![("position".to_string(), 1)]);
+        assert_eq!(reasons, vec![
+            "position 0 is out of range for `ab`, expected 1-2",
+            "position 7 is out of range for `bbb`, expected 1-3",
+        ]);
+    }
+}
@@ -0,0 +1,355 @@
+use std::collections::{BTreeMap, HashSet};
+use std::str::{CharIndices, FromStr};
+
+use regex::Regex;
+use unicode_segmentation::{Graphemes, UnicodeSegmentation};
+
+use crate::error::{Error, Result};
+use super::Password;
+
+pub trait PasswordPolicy {
+    fn name(&self) -> &str;
+
+    /// Returns a validation error saying why `p` doesn't satisfy the policy.
+    fn check(&self, p: &Password) -> Result<()>;
+}
+
+/// What a position or an occurrence in a password refers to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Unit {
+    Char,
+    Grapheme,
+}
+
+impl Unit {
+    pub fn iter(self, s: &str) -> Units<'_> {
+        match self {
+            Unit::Char => Units::Chars(s, s.char_indices()),
+            Unit::Grapheme => Units::Graphemes(s.graphemes(true)),
+        }
+    }
+}
+
+impl FromStr for Unit {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Unit> {
+        match s {
+            "chars" => Ok(Unit::Char),
+            "graphemes" => Ok(Unit::Grapheme),
+            _ => Err(Error::validation(format!("unknown unit `{}`, expected `chars` or `graphemes`", s))),
+        }
+    }
+}
+
+/// Iterates over the units of a string as string slices.
+pub enum Units<'a> {
+    Chars(&'a str, CharIndices<'a>),
+    Graphemes(Graphemes<'a>),
+}
+
+impl<'a> Iterator for Units<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        match self {
+            Units::Chars(s, chars) => chars.next().map(|(i, c)| &s[i..i + c.len_utf8()]),
+            Units::Graphemes(graphemes) => graphemes.next(),
+        }
+    }
+}
+
+/// The symbol has to occur between `min` and `max` times.
+pub struct Occurrence(pub Unit);
+
+impl PasswordPolicy for Occurrence {
+    fn name(&self) -> &str {
+        "occurrence"
+    }
+
+    fn check(&self, p: &Password) -> Result<()> {
+        let (min, max) = p.parameters;
+        let occurances = self.0.iter(&p.password).filter(|&u| u == p.symbol).count();
+
+        if occurances >= min && occurances <= max {
+            Ok(())
+        } else {
+            Err(Error::validation(format!("`{}` occurs {} times, expected {}-{}", p.symbol, occurances, min, max)))
+        }
+    }
+}
+
+/// The symbol has to be at exactly one of the two (1-based) positions.
+pub struct Position(pub Unit);
+
+impl PasswordPolicy for Position {
+    fn name(&self) -> &str {
+        "position"
+    }
+
+    fn check(&self, p: &Password) -> Result<()> {
+        let (a, b) = p.parameters;
+
+        let has_symbol_at = |pos: usize| {
+            let unit = pos.checked_sub(1).and_then(|i| self.0.iter(&p.password).nth(i));
+
+            match unit {
+                Some(u) => Ok(u == p.symbol),
+                None => Err(Error::validation(format!(
+                    "position {} is out of range for `{}`, expected 1-{}",
+                    pos, p.password, self.0.iter(&p.password).count()
+                ))),
+            }
+        };
+
+        match (has_symbol_at(a)?, has_symbol_at(b)?) {
+            (true, false) | (false, true) => Ok(()),
+            (true, true) => Err(Error::validation(format!("`{}` is at both position {} and {}", p.symbol, a, b))),
+            (false, false) => Err(Error::validation(format!("`{}` is at neither position {} nor {}", p.symbol, a, b))),
+        }
+    }
+}
+
+/// The password has to match a regular expression.
+pub struct Matches(pub Regex);
+
+impl PasswordPolicy for Matches {
+    fn name(&self) -> &str {
+        "regex"
+    }
+
+    fn check(&self, p: &Password) -> Result<()> {
+        if self.0.is_match(&p.password) {
+            Ok(())
+        } else {
+            Err(Error::validation(format!("`{}` doesn't match `{}`", p.password, self.0)))
+        }
+    }
+}
+
+/// The password has to contain at least this many different characters.
+pub struct MinDistinctChars(pub usize);
+
+impl PasswordPolicy for MinDistinctChars {
+    fn name(&self) -> &str {
+        "min-distinct"
+    }
+
+    fn check(&self, p: &Password) -> Result<()> {
+        let distinct = p.password.chars().collect::<HashSet<_>>().len();
+
+        if distinct >= self.0 {
+            Ok(())
+        } else {
+            Err(Error::validation(format!("has {} distinct characters, expected at least {}", distinct, self.0)))
+        }
+    }
+}
+
+/// The password may not contain any of these substrings.
+pub struct Forbidden(pub Vec<String>);
+
+impl PasswordPolicy for Forbidden {
+    fn name(&self) -> &str {
+        "forbidden"
+    }
+
+    fn check(&self, p: &Password) -> Result<()> {
+        match self.0.iter().find(|s| p.password.contains(s.as_str())) {
+            Some(s) => Err(Error::validation(format!("contains forbidden `{}`", s))),
+            None => Ok(()),
+        }
+    }
+}
+
+type Constructor = Box<dyn Fn(Option<&str>) -> Result<Box<dyn PasswordPolicy>>>;
+
+/// Creates policies by name. A policy is selected with a spec of the form
+/// `name` or `name:argument`, e.g. `min-distinct:4` or `forbidden:abc,123`.
+pub struct PolicyRegistry {
+    constructors: BTreeMap<String, Constructor>,
+}
+
+fn unit(arg: Option<&str>) -> Result<Unit> {
+    arg.map(|a| a.parse()).unwrap_or(Ok(Unit::Char))
+}
+
+fn argument<'a>(name: &str, arg: Option<&'a str>) -> Result<&'a str> {
+    arg.ok_or_else(|| Error::validation(format!("policy `{}` needs an argument, use `{}:<argument>`", name, name)))
+}
+
+impl PolicyRegistry {
+    pub fn empty() -> Self {
+        PolicyRegistry { constructors: BTreeMap::new() }
+    }
+
+    /// A registry with all built-in policies.
+    pub fn new() -> Self {
+        let mut registry = Self::empty();
+
+        registry.register("occurrence", |arg| {
+            Ok(Box::new(Occurrence(unit(arg)?)))
+        });
+
+        registry.register("position", |arg| {
+            Ok(Box::new(Position(unit(arg)?)))
+        });
+
+        registry.register("regex", |arg| {
+            let pattern = argument("regex", arg)?;
+            let re = Regex::new(pattern)
+                .map_err(|e| Error::validation(format!("invalid regex `{}`: {}", pattern, e)))?;
+            Ok(Box::new(Matches(re)))
+        });
+
+        registry.register("min-distinct", |arg| {
+            let arg = argument("min-distinct", arg)?;
+            let min = arg
+                .parse()
+                .map_err(|_| Error::validation(format!("invalid count `{}`", arg)))?;
+            Ok(Box::new(MinDistinctChars(min)))
+        });
+
+        registry.register("forbidden", |arg| {
+            let words = argument("forbidden", arg)?
+                .split(',')
+                .filter(|w| !w.is_empty())
+                .map(|w| w.to_string())
+                .collect();
+            Ok(Box::new(Forbidden(words)))
+        });
+
+        registry
+    }
+
+    pub fn register<F>(&mut self, name: &str, constructor: F)
+    where
+        F: Fn(Option<&str>) -> Result<Box<dyn PasswordPolicy>> + 'static,
+    {
+        self.constructors.insert(name.to_string(), Box::new(constructor));
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.constructors.keys().map(|k| k.as_str())
+    }
+
+    pub fn create(&self, spec: &str) -> Result<Box<dyn PasswordPolicy>> {
+        let (name, arg) = match spec.find(':') {
+            Some(i) => (&spec[..i], Some(&spec[i + 1..])),
+            None => (spec, None),
+        };
+
+        let constructor = self.constructors.get(name).ok_or_else(|| {
+            let known: Vec<&str> = self.names().collect();
+            Error::validation(format!("unknown policy `{}`, expected one of {}", name, known.join(", ")))
+        })?;
+
+        constructor(arg)
+    }
+}
+
+impl Default for PolicyRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(policy: &dyn PasswordPolicy, p: &str) -> Result<()> {
+        policy.check(&p.parse().unwrap())
+    }
+
+    #[test]
+    fn test_builtin_policies() {
+        let occurrence = Occurrence(Unit::Char);
+        assert_eq!(check(&occurrence, "1-3 a: abcde"), Ok(()));
+        assert_eq!(check(&occurrence, "1-3 b: cdefg"), Err(Error::validation("`b` occurs 0 times, expected 1-3")));
+
+        let position = Position(Unit::Char);
+        assert_eq!(check(&position, "1-3 a: abcde"), Ok(()));
+        assert_eq!(check(&position, "1-3 b: cdefg"), Err(Error::validation("`b` is at neither position 1 nor 3")));
+        assert_eq!(check(&position, "2-9 c: ccccccccc"), Err(Error::validation("`c` is at both position 2 and 9")));
+        assert_eq!(check(&position, "0-3 a: abcde"), Err(Error::validation("position 0 is out of range for `abcde`, expected 1-5")));
+        assert_eq!(check(&position, "1-6 a: abcde"), Err(Error::validation("position 6 is out of range for `abcde`, expected 1-5")));
+    }
+
+    #[test]
+    fn test_units() {
+        let s = "ae\u{301}👍🏽";
+
+        assert_eq!(Unit::Char.iter(s).collect::<Vec<_>>(), vec!["a", "e", "\u{301}", "👍", "🏽"]);
+        assert_eq!(Unit::Grapheme.iter(s).collect::<Vec<_>>(), vec!["a", "e\u{301}", "👍🏽"]);
+    }
+
+    #[test]
+    fn test_unicode_policies() {
+        assert_eq!(check(&Occurrence(Unit::Char), "2-3 é: éclairété"), Ok(()));
+        assert_eq!(check(&Position(Unit::Char), "1-5 ß: ßtraß"), Err(Error::validation("`ß` is at both position 1 and 5")));
+        assert_eq!(check(&Position(Unit::Char), "1-4 ?: ab1?"), Ok(()));
+
+        // a decomposed é is two chars but a single grapheme
+        let decomposed = "1-2 x: e\u{301}x";
+        assert_eq!(check(&Position(Unit::Char), decomposed), Err(Error::validation("`x` is at neither position 1 nor 2")));
+        assert_eq!(check(&Position(Unit::Grapheme), decomposed), Ok(()));
+        assert_eq!(check(&Position(Unit::Grapheme), "1-3 x: e\u{301}x"), Err(Error::validation("position 3 is out of range for `e\u{301}x`, expected 1-2")));
+
+        assert_eq!(check(&Occurrence(Unit::Char), "2-2 e\u{301}: e\u{301}te\u{301}"), Err(Error::validation("`e\u{301}` occurs 0 times, expected 2-2")));
+        assert_eq!(check(&Occurrence(Unit::Grapheme), "2-2 e\u{301}: e\u{301}te\u{301}"), Ok(()));
+    }
+
+    #[test]
+    fn test_registry() {
+        let registry = PolicyRegistry::new();
+
+        assert_eq!(registry.names().collect::<Vec<_>>(), vec!["forbidden", "min-distinct", "occurrence", "position", "regex"]);
+
+        let regex = registry.create("regex:^[a-c]+$").unwrap();
+        assert_eq!(check(regex.as_ref(), "1-3 a: abcab"), Ok(()));
+        assert_eq!(check(regex.as_ref(), "1-3 a: abcde"), Err(Error::validation("`abcde` doesn't match `^[a-c]+$`")));
+
+        let distinct = registry.create("min-distinct:4").unwrap();
+        assert_eq!(check(distinct.as_ref(), "1-3 a: abcd"), Ok(()));
+        assert_eq!(check(distinct.as_ref(), "1-3 a: abcabc"), Err(Error::validation("has 3 distinct characters, expected at least 4")));
+
+        let forbidden = registry.create("forbidden:abc,xyz").unwrap();
+        assert_eq!(check(forbidden.as_ref(), "1-3 a: axyzb"), Err(Error::validation("contains forbidden `xyz`")));
+
+        let graphemes = registry.create("position:graphemes").unwrap();
+        assert_eq!(check(graphemes.as_ref(), "1-2 x: e\u{301}x"), Ok(()));
+
+        assert!(registry.create("occurrence:5").is_err());
+        assert!(registry.create("regex").is_err());
+        assert!(registry.create("regex:(").is_err());
+        assert!(registry.create("min-distinct:many").is_err());
+        assert!(registry.create("entropy").is_err());
+    }
+
+    struct MaxLength(usize);
+
+    impl PasswordPolicy for MaxLength {
+        fn name(&self) -> &str {
+            "max-length"
+        }
+
+        fn check(&self, p: &Password) -> Result<()> {
+            if p.password.len() <= self.0 { Ok(()) } else { Err(Error::validation("too long")) }
+        }
+    }
+
+    #[test]
+    fn test_register_custom_policy() {
+        let mut registry = PolicyRegistry::empty();
+        registry.register("max-length", |arg| {
+            let max = arg.unwrap_or("8").parse().map_err(|_| Error::validation("invalid length"))?;
+            Ok(Box::new(MaxLength(max)))
+        });
+
+        let policy = registry.create("max-length:3").unwrap();
+        assert_eq!(policy.name(), "max-length");
+        assert_eq!(check(policy.as_ref(), "1-3 a: abcd"), Err(Error::validation("too long")));
+        assert!(registry.create("occurrence").is_err());
+    }
+}
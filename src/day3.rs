@@ -1,130 +1,720 @@
 #![allow(dead_code)]
-use std::str::FromStr;
+use std::collections::HashMap;
 use std::convert::TryFrom;
-use std::convert::TryInto;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+use crate::error::{Error, Result};
+use crate::grid::{parse_rows, Grid};
+use crate::input::Mode;
+use crate::solution::Solution;
 
-enum Object {
+pub struct Day3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Object {
     Empty,
-    Tree
+    Tree,
+    /// Stops the toboggan, the run ends here.
+    Rock,
+    /// The toboggan slides over the next square without touching it.
+    Ice,
 }
 
-struct Row { objects: Vec<Object> }
+/// Which character stands for which object, and what hitting each object
+/// costs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Terrain {
+    chars: Vec<(char, Object)>,
+    costs: HashMap<Object, usize>,
+}
 
-struct Map { rows: Vec<Row> }
+impl Terrain {
+    /// The terrain of the puzzle: `.` is open and free, `#` is a tree and
+    /// costs 1, so the cost of a run is its number of trees.
+    pub fn new() -> Terrain {
+        Terrain { chars: vec![], costs: HashMap::new() }
+            .with_char('.', Object::Empty)
+            .with_char('#', Object::Tree)
+            .with_cost(Object::Tree, 1)
+    }
 
-struct Position { x: usize, y: usize }
+    /// Reads `c` as `object`, replacing what `c` meant before.
+    pub fn with_char(mut self, c: char, object: Object) -> Terrain {
+        self.chars.retain(|&(other, _)| other != c);
+        self.chars.push((c, object));
+        self
+    }
 
-impl TryFrom<char> for Object {
-    type Error = String;
+    pub fn with_cost(mut self, object: Object, cost: usize) -> Terrain {
+        self.costs.insert(object, cost);
+        self
+    }
 
-    fn try_from(c: char) -> Result<Self, Self::Error> {
-        match c {
-            '.' => Ok(Object::Empty),
-            '#' => Ok(Object::Tree),
-            _ => Err("invalid object char".to_string()),
-        }
+    fn object(&self, c: char) -> Option<Object> {
+        self.chars.iter().find(|&&(other, _)| other == c).map(|&(_, object)| object)
+    }
+
+    fn char(&self, object: Object) -> Option<char> {
+        self.chars.iter().find(|&&(_, other)| other == object).map(|&(c, _)| c)
+    }
+
+    /// Objects without a cost are free.
+    pub fn cost(&self, object: Object) -> usize {
+        self.costs.get(&object).copied().unwrap_or(0)
     }
 }
 
-impl FromStr for Row {
-    type Err = String;
+impl Default for Terrain {
+    fn default() -> Terrain {
+        Terrain::new()
+    }
+}
+
+/// What happens when a toboggan reaches an edge of the map.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Topology {
+    /// The run ends at any edge.
+    Bounded,
+    /// The map repeats to the right, the run ends at the bottom.
+    Cylinder,
+    /// The map repeats in both directions.
+    Torus,
+    /// The toboggan bounces off the left and right edges, the run ends at
+    /// the bottom.
+    Reflecting,
+}
 
-    fn from_str(input: &str) -> Result<Row, String> {
-        let mut objects = vec![];
+impl FromStr for Topology {
+    type Err = Error;
 
-        for c in input.chars() {
-            let obj: Object = c.try_into()?;
-            objects.push(obj);
+    fn from_str(s: &str) -> Result<Topology> {
+        match s {
+            "bounded" => Ok(Topology::Bounded),
+            "cylinder" => Ok(Topology::Cylinder),
+            "torus" => Ok(Topology::Torus),
+            "reflecting" => Ok(Topology::Reflecting),
+            _ => Err(Error::parse(format!("unknown topology `{}`, expected bounded, cylinder, torus or reflecting", s))),
         }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Map { grid: Grid<Object>, topology: Topology, terrain: Terrain }
 
-        Ok(Row { objects })
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Position { pub x: usize, pub y: usize }
+
+/// Folds `x` back into `0..len` as if bouncing between both ends.
+fn reflect(x: usize, len: usize) -> usize {
+    if len <= 1 {
+        return 0;
     }
+
+    let period = 2 * (len - 1);
+    let x = x % period;
+
+    if x < len { x } else { period - x }
 }
 
-impl TryFrom<Vec<String>> for Map {
-    type Error = String;
+/// The positions visited when moving from the top left corner, each on the
+/// unfolded plane and on the map. A walk that gets back to the start would
+/// repeat itself from there, so it ends. It also ends on a rock, and passes
+/// over the square after ice.
+struct Walk<'a> {
+    map: &'a Map,
+    movement: Position,
+    step: usize,
+    sliding: bool,
+    stopped: bool,
+}
+
+impl Iterator for Walk<'_> {
+    type Item = (Position, Position);
 
-    fn try_from(input: Vec<String>) -> Result<Map, String> {
-        let mut rows: Vec<Row> = vec![];
+    fn next(&mut self) -> Option<(Position, Position)> {
+        loop {
+            if self.stopped {
+                return None;
+            }
+
+            let unfolded = Position {
+                x: self.movement.x.checked_mul(self.step)?,
+                y: self.movement.y.checked_mul(self.step)?,
+            };
+            let pos = self.map.locate(&unfolded)?;
+
+            if self.step > 0 && pos == (Position { x: 0, y: 0 }) {
+                return None;
+            }
+
+            self.step += 1;
 
-        for line in input.iter() {
-            let row = line.parse()?;
-            rows.push(row);
+            if self.sliding {
+                self.sliding = false;
+                continue;
+            }
+
+            match self.map.grid.get(pos.x, pos.y) {
+                Some(Object::Rock) => self.stopped = true,
+                Some(Object::Ice) => self.sliding = true,
+                _ => (),
+            }
+
+            return Some((unfolded, pos));
         }
+    }
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// Tree counts for the slopes with one `dy`, on a map that only repeats
+/// horizontally. Step `k` is on row `k * dy`, in the column of `k * dx`
+/// modulo the period `p`, and those columns come round again every
+/// `p / gcd(p, dx)` steps. So the trees of every column are summed once per
+/// class of steps, and a slope adds up one sum per class instead of walking
+/// every row.
+struct ColumnClasses<'a> {
+    map: &'a Map,
+    dy: usize,
+    period: usize,
+    steps: usize,
+    /// Trees per class and column, by the number of classes.
+    sums: HashMap<usize, Vec<Vec<usize>>>,
+}
+
+impl<'a> ColumnClasses<'a> {
+    /// `None` when the slopes can't be counted this way: the topology
+    /// repeats vertically or not at all, a walk along a row comes back to
+    /// the start instead of leaving the map, or rocks and ice change where
+    /// a walk goes.
+    fn new(map: &'a Map, dy: usize) -> Option<ColumnClasses<'a>> {
+        let period = match map.periods() {
+            (Some(period), None) if period > 0 => period,
+            _ => return None,
+        };
 
-        Ok(Map { rows })
+        if dy == 0 || map.grid.is_empty() || map.grid.iter().any(|(_, _, o)| matches!(o, Object::Rock | Object::Ice)) {
+            return None;
+        }
+
+        let steps = (map.grid.height() - 1) / dy + 1;
+        Some(ColumnClasses { map, dy, period, steps, sums: HashMap::new() })
+    }
+
+    fn trees(&mut self, dx: usize) -> usize {
+        let dx = dx % self.period;
+        let classes = (self.period / gcd(self.period, dx)).min(self.steps);
+        let (map, dy, period) = (self.map, self.dy, self.period);
+
+        let sums = self.sums.entry(classes).or_insert_with(|| {
+            let mut sums = vec![vec![0; map.grid.width()]; classes];
+
+            for (k, row) in map.grid.rows().step_by(dy).enumerate() {
+                for (x, object) in row.iter().enumerate() {
+                    if *object == Object::Tree {
+                        sums[k % classes][x] += 1;
+                    }
+                }
+            }
+
+            sums
+        });
+
+        (0..classes)
+            .filter_map(|j| map.locate(&Position { x: j * dx % period, y: 0 }).map(|pos| sums[j][pos.x]))
+            .sum()
+    }
+}
+
+impl FromStr for Map {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Map> {
+        Map::parse_with(input, Mode::Strict)
+    }
+}
+
+impl TryFrom<Vec<String>> for Map {
+    type Error = Error;
+
+    fn try_from(input: Vec<String>) -> Result<Map> {
+        input.join("\n").parse()
     }
 }
 
 impl Map {
+    fn new(grid: Grid<Object>, topology: Topology, terrain: Terrain) -> Map {
+        Map { grid, topology, terrain }
+    }
+
+    /// Parses a map, rows of different lengths are an error naming the line
+    /// in strict mode; in lenient mode short rows are padded with open
+    /// squares. Empty rows are rejected in both modes.
+    pub fn parse_with(input: &str, mode: Mode) -> Result<Map> {
+        Map::parse_terrain(input, mode, Terrain::new())
+    }
+
+    /// Like `parse_with`, reading the characters through `terrain`.
+    pub fn parse_terrain(input: &str, mode: Mode, terrain: Terrain) -> Result<Map> {
+        let rows = parse_rows::<char>(input)?
+            .into_iter()
+            .enumerate()
+            .map(|(y, row)| {
+                row.into_iter()
+                    .enumerate()
+                    .map(|(x, c)| {
+                        terrain.object(c).ok_or_else(|| {
+                            Error::parse(format!("invalid object char `{}`", c)).at_column(x + 1).at_line(y + 1)
+                        })
+                    })
+                    .collect::<Result<Vec<Object>>>()
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let grid = match mode {
+            Mode::Strict => Grid::from_rows(rows)?,
+            Mode::Lenient => Grid::from_rows_padded(rows, Object::Empty),
+        };
+
+        Ok(Map::new(grid, Topology::Cylinder, terrain))
+    }
+
+    pub fn with_topology(self, topology: Topology) -> Map {
+        Map { topology, ..self }
+    }
+
+    /// Maps a position on the unfolded plane onto the map, `None` when it
+    /// is off the map for this topology.
+    fn locate(&self, pos: &Position) -> Option<Position> {
+        let (width, height) = (self.grid.width(), self.grid.height());
+
+        if self.grid.is_empty() {
+            return None;
+        }
+
+        let (x, y) = match self.topology {
+            Topology::Bounded => (pos.x, pos.y),
+            Topology::Cylinder => (pos.x % width, pos.y),
+            Topology::Torus => (pos.x % width, pos.y % height),
+            Topology::Reflecting => (reflect(pos.x, width), pos.y),
+        };
+
+        if x < width && y < height { Some(Position { x, y }) } else { None }
+    }
+
     fn get_object_at_pos(&self, pos: &Position) -> Option<&Object> {
-        let row = self.rows.get(pos.y)?;
-        let row_length = row.objects.len();
-        row.objects.get(pos.x % row_length)
+        let pos = self.locate(pos)?;
+        self.grid.get(pos.x, pos.y)
     }
 
-    fn number_of_trees_for_movement(&self, movement: &Position) -> usize {
-        let mut pos = Position { x: 0, y: 0 };
-        let mut num_trees = 0;
+    fn walk(&self, movement: &Position) -> Walk<'_> {
+        Walk { map: self, movement: *movement, step: 0, sliding: false, stopped: false }
+    }
 
-        loop {
-            match self.get_object_at_pos(&pos) {
-                Some(Object::Tree) => num_trees += 1,
-                None => break,
-                _ => (),
+    /// Every position visited for `movement`, with what is there.
+    pub fn trace(&self, movement: &Position) -> Vec<(Position, Object)> {
+        self.walk(movement)
+            .filter_map(|(_, pos)| self.grid.get(pos.x, pos.y).map(|object| (pos, *object)))
+            .collect()
+    }
+
+    /// Draws the map with the route for `movement` on top of it, `O` where
+    /// any other square is hit and `X` where a tree is, like the puzzle
+    /// statement. On a cylinder the map is repeated to the right until the
+    /// whole route fits. The route is drawn for the smallest movement that
+    /// visits the same squares, so a large step to the right doesn't make
+    /// the drawing any wider.
+    pub fn render(&self, movement: &Position) -> String {
+        let width = self.grid.width();
+
+        // route positions in the coordinates of the drawing
+        let hits: HashMap<Position, Object> = self
+            .walk(&self.canonical(movement))
+            .filter_map(|(unfolded, pos)| {
+                let object = *self.grid.get(pos.x, pos.y)?;
+
+                Some(match self.topology {
+                    Topology::Cylinder => (Position { x: unfolded.x, y: pos.y }, object),
+                    _ => (pos, object),
+                })
+            })
+            .collect();
+        let tiles = hits.keys().map(|pos| pos.x / width.max(1) + 1).max().unwrap_or(1);
+
+        let mut out = String::new();
+
+        for (y, row) in self.grid.rows().enumerate() {
+            for x in 0..width * tiles {
+                out.push(match hits.get(&Position { x, y }) {
+                    Some(Object::Tree) => 'X',
+                    Some(_) => 'O',
+                    None => self.terrain.char(row[x % width]).unwrap_or('?'),
+                });
             }
 
-            pos = Position {
-                x: pos.x + movement.x,
-                y: pos.y + movement.y
-            };
+            out.push('\n');
         }
 
-        num_trees
+        out
+    }
+
+    fn number_of_trees_for_movement(&self, movement: &Position) -> usize {
+        self.walk(movement)
+            .filter(|(_, pos)| self.grid.get(pos.x, pos.y) == Some(&Object::Tree))
+            .count()
+    }
+
+    /// The summed cost of every square hit for `movement`.
+    pub fn cost_for_movement(&self, movement: &Position) -> usize {
+        self.trace(movement).iter().map(|&(_, object)| self.terrain.cost(object)).sum()
+    }
+
+    /// The distance along each axis after which the topology repeats, `None`
+    /// when it doesn't.
+    fn periods(&self) -> (Option<usize>, Option<usize>) {
+        let (width, height) = (self.grid.width(), self.grid.height());
+
+        match self.topology {
+            Topology::Bounded => (None, None),
+            Topology::Cylinder => (Some(width), None),
+            Topology::Torus => (Some(width), Some(height)),
+            Topology::Reflecting => (Some((2 * width.saturating_sub(1)).max(1)), None),
+        }
     }
-}
 
+    /// The smallest movement that visits the same cells as `movement`: a
+    /// step of a whole period along an axis lands on the same cell.
+    fn canonical(&self, movement: &Position) -> Position {
+        let (px, py) = self.periods();
 
-fn part_one(input: Vec<String>) -> Result<usize, String> {
-    let map: Map = input.try_into()?;
-    let movement = Position { x: 3, y: 1 };
-    let num_trees = map.number_of_trees_for_movement(&movement);
-    Ok(num_trees)
+        Position {
+            x: px.filter(|&p| p > 0).map_or(movement.x, |p| movement.x % p),
+            y: py.filter(|&p| p > 0).map_or(movement.y, |p| movement.y % p),
+        }
+    }
+
+    /// Counts the trees for every slope in the given ranges, fewest trees
+    /// first; ties keep the order of `dy`, then `dx`. Standing still isn't a
+    /// slope. On a cylinder or reflecting map the trees are summed per
+    /// column class (see `ColumnClasses`), shared by every `dx` with the
+    /// same number of classes. Other maps are walked, once per movement up
+    /// to whole periods of the topology.
+    pub fn rank_slopes(&self, dx: RangeInclusive<usize>, dy: RangeInclusive<usize>) -> Vec<(Position, usize)> {
+        let mut counts: HashMap<Position, usize> = HashMap::new();
+        let mut ranked = vec![];
+
+        for y in dy {
+            let mut classes = ColumnClasses::new(self, y);
+
+            for x in dx.clone() {
+                if x == 0 && y == 0 {
+                    continue;
+                }
+
+                let movement = Position { x, y };
+                let trees = match classes.as_mut() {
+                    Some(classes) => classes.trees(x),
+                    None => *counts
+                        .entry(self.canonical(&movement))
+                        .or_insert_with(|| self.number_of_trees_for_movement(&movement)),
+                };
+
+                ranked.push((movement, trees));
+            }
+        }
+
+        ranked.sort_by_key(|&(_, trees)| trees);
+        ranked
+    }
+
+    /// The slope in the given ranges that hits the fewest trees.
+    pub fn best_slope(&self, dx: RangeInclusive<usize>, dy: RangeInclusive<usize>) -> Option<(Position, usize)> {
+        self.rank_slopes(dx, dy).into_iter().next()
+    }
 }
 
-fn part_two(input: Vec<String>) -> Result<usize, String> {
-    let map: Map = input.try_into()?;
 
-    let movements: Vec<Position> = vec![
-        Position { x: 1, y: 1 },
-        Position { x: 3, y: 1 },
-        Position { x: 5, y: 1 },
-        Position { x: 7, y: 1 },
-        Position { x: 1, y: 2 },
-    ];
+impl Solution for Day3 {
+    const DAY: u8 = 3;
 
-    Ok(
-        movements
-            .iter()
-            .map(|m| map.number_of_trees_for_movement(m))
-            .fold(1, |sum, trees| sum * trees)
-    )
+    type Input = Map;
+    type Answer = usize;
+
+    fn parse(input: &str) -> Result<Map> {
+        input.parse()
+    }
+
+    fn part_one(map: &Map) -> Result<usize> {
+        let movement = Position { x: 3, y: 1 };
+        Ok(map.number_of_trees_for_movement(&movement))
+    }
+
+    fn part_two(map: &Map) -> Result<usize> {
+        let movements: Vec<Position> = vec![
+            Position { x: 1, y: 1 },
+            Position { x: 3, y: 1 },
+            Position { x: 5, y: 1 },
+            Position { x: 7, y: 1 },
+            Position { x: 1, y: 2 },
+        ];
+
+        Ok(
+            movements
+                .iter()
+                .map(|m| map.number_of_trees_for_movement(m))
+                .product()
+        )
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::input::{read_lines};
+    use crate::input::{read_file};
     use super::*;
 
     #[test]
     fn test_part_one() {
-        let input = read_lines("input/day3.txt");
-        assert_eq!(part_one(input), Ok(299));
+        let map = Day3::parse(&read_file("input/day3.txt").unwrap()).unwrap();
+        assert_eq!(Day3::part_one(&map), Ok(299));
     }
 
     #[test]
     fn test_part_two() {
-        let input = read_lines("input/day3.txt");
-        assert_eq!(part_two(input), Ok(3621285278));
+        let map = Day3::parse(&read_file("input/day3.txt").unwrap()).unwrap();
+        assert_eq!(Day3::part_two(&map), Ok(3621285278));
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert_eq!(
+            Day3::parse("..#\n.#.\n#.o\n").err(),
+            Some(Error::Parse { line: Some(3), column: Some(3), field: None, reason: "invalid object char `o`".to_string() })
+        );
+    }
+
+    #[test]
+    fn test_rectangular() {
+        assert_eq!(
+            Map::try_from(vec!["..#".to_string(), "#.".to_string(), ".#.".to_string()]).err(),
+            Some(Error::parse("row has 2 cells, expected 3").at_line(2))
+        );
+        assert_eq!(Day3::parse("..#\n\n.#.\n").err(), Some(Error::parse("row is empty").at_line(2)));
+        assert_eq!(Map::parse_with("..#\n   \n#..\n", Mode::Lenient).err(), Some(Error::parse("row is empty").at_line(2)));
+
+        let map = Map::parse_with("..#\n#\n.#.#\n", Mode::Lenient).unwrap();
+        assert_eq!((map.grid.width(), map.grid.height()), (4, 3));
+        assert_eq!(map.get_object_at_pos(&Position { x: 0, y: 1 }), Some(&Object::Tree));
+        assert_eq!(map.get_object_at_pos(&Position { x: 3, y: 1 }), Some(&Object::Empty));
+        assert_eq!(map.get_object_at_pos(&Position { x: 3, y: 0 }), Some(&Object::Empty));
+        assert_eq!(map.get_object_at_pos(&Position { x: 7, y: 2 }), Some(&Object::Tree));
+    }
+
+    fn map(topology: Topology) -> Map {
+        ".#..\n..#.\n#...\n.#.#\n..#.\n".parse::<Map>().unwrap().with_topology(topology)
+    }
+
+    fn visited(map: &Map, x: usize, y: usize) -> Vec<(usize, usize)> {
+        map.walk(&Position { x, y }).map(|(_, p)| (p.x, p.y)).collect()
+    }
+
+    #[test]
+    fn test_topologies() {
+        let bounded = map(Topology::Bounded);
+        assert_eq!(visited(&bounded, 1, 1), vec![(0, 0), (1, 1), (2, 2), (3, 3)]);
+        assert_eq!(visited(&bounded, 3, 1), vec![(0, 0), (3, 1)]);
+        assert_eq!(bounded.number_of_trees_for_movement(&Position { x: 1, y: 1 }), 1);
+
+        let cylinder = map(Topology::Cylinder);
+        assert_eq!(visited(&cylinder, 3, 1), vec![(0, 0), (3, 1), (2, 2), (1, 3), (0, 4)]);
+        assert_eq!(cylinder.number_of_trees_for_movement(&Position { x: 3, y: 1 }), 1);
+
+        let reflecting = map(Topology::Reflecting);
+        assert_eq!(visited(&reflecting, 2, 1), vec![(0, 0), (2, 1), (2, 2), (0, 3), (2, 4)]);
+        assert_eq!(reflecting.number_of_trees_for_movement(&Position { x: 2, y: 1 }), 2);
+
+        // 4 columns and 5 rows: the path covers every cell before it repeats
+        let torus = map(Topology::Torus);
+        let path = visited(&torus, 1, 1);
+        assert_eq!(path.len(), 20);
+        assert_eq!(&path[..6], &[(0, 0), (1, 1), (2, 2), (3, 3), (0, 4), (1, 0)]);
+        assert_eq!(torus.number_of_trees_for_movement(&Position { x: 1, y: 1 }), 6);
+        assert_eq!(visited(&torus, 2, 5), vec![(0, 0), (2, 0)]);
+        assert_eq!(visited(&torus, 0, 0), vec![(0, 0)]);
+
+        // sideways walks end once they are back at the start
+        assert_eq!(visited(&cylinder, 3, 0), vec![(0, 0), (3, 0), (2, 0), (1, 0)]);
+        assert_eq!(visited(&reflecting, 2, 0), vec![(0, 0), (2, 0), (2, 0)]);
+        assert_eq!(visited(&bounded, 0, 0), vec![(0, 0)]);
+    }
+
+    const EXAMPLE: &str = "..##.......\n#...#...#..\n.#....#..#.\n..#.#...#.#\n.#...##..#.\n..#.##.....\n.#.#.#....#\n.#........#\n#.##...#...\n#...##....#\n.#..#...#.#\n";
+
+    #[test]
+    fn test_trace() {
+        let map: Map = EXAMPLE.parse().unwrap();
+        let trace = map.trace(&Position { x: 3, y: 1 });
+
+        assert_eq!(trace.len(), 11);
+        assert_eq!(&trace[..3], &[
+            (Position { x: 0, y: 0 }, Object::Empty),
+            (Position { x: 3, y: 1 }, Object::Empty),
+            (Position { x: 6, y: 2 }, Object::Tree),
+        ]);
+        assert_eq!(trace[4], (Position { x: 1, y: 4 }, Object::Tree));
+        assert_eq!(trace.iter().filter(|(_, o)| *o == Object::Tree).count(), 7);
+    }
+
+    #[test]
+    fn test_render() {
+        let map: Map = EXAMPLE.parse().unwrap();
+        let rendered = map.render(&Position { x: 3, y: 1 });
+        let lines: Vec<&str> = rendered.lines().collect();
+
+        // as drawn in the puzzle statement, the map repeated three times
+        assert_eq!(lines.len(), 11);
+        assert_eq!(lines[0], "O.##.........##.........##.......");
+        assert_eq!(lines[1], "#..O#...#..#...#...#..#...#...#..");
+        assert_eq!(lines[2], ".#....X..#..#....#..#..#....#..#.");
+        assert_eq!(lines[10], ".#..#...#.#.#..#...#.#.#..#...X.#");
+
+        let torus = map.with_topology(Topology::Torus);
+        assert_eq!(torus.render(&Position { x: 1, y: 1 }).lines().next(), Some("O.##......."));
+    }
+
+    #[test]
+    fn test_render_large_step() {
+        let map: Map = EXAMPLE.parse().unwrap();
+        let rendered = map.render(&Position { x: 3 + 11 * 100_000, y: 1 });
+
+        assert_eq!(rendered, map.render(&Position { x: 3, y: 1 }));
+        assert_eq!(rendered.lines().next().map(str::len), Some(33));
+    }
+
+    #[test]
+    fn test_render_ice_on_cylinder() {
+        let terrain = Terrain::new().with_char('~', Object::Ice);
+        let map = Map::parse_terrain("....\n.~..\n..#.\n.#.#\n....\n", Mode::Strict, terrain).unwrap();
+
+        // the square after the ice is skipped, so the tree at (3, 3) is hit
+        // on the first tile and the last square on the second
+        assert_eq!(map.render(&Position { x: 1, y: 1 }), "O.......\n.O...~..\n..#...#.\n.#.X.#.#\n....O...\n");
+    }
+
+    fn icy_terrain() -> Terrain {
+        Terrain::new()
+            .with_char('@', Object::Rock)
+            .with_char('~', Object::Ice)
+            .with_cost(Object::Rock, 10)
+            .with_cost(Object::Tree, 2)
+    }
+
+    #[test]
+    fn test_terrain() {
+        let map = Map::parse_terrain("....\n.~..\n..#.\n.#.#\n....\n", Mode::Strict, icy_terrain()).unwrap();
+        let movement = Position { x: 1, y: 1 };
+
+        // the ice at (1, 1) carries the toboggan over the tree at (2, 2)
+        assert_eq!(visited(&map, 1, 1), vec![(0, 0), (1, 1), (3, 3), (0, 4)]);
+        assert_eq!(map.number_of_trees_for_movement(&movement), 1);
+        assert_eq!(map.cost_for_movement(&movement), 2);
+
+        let map = Map::parse_terrain("....\n.#..\n..@.\n...#\n", Mode::Strict, icy_terrain()).unwrap();
+        assert_eq!(visited(&map, 1, 1), vec![(0, 0), (1, 1), (2, 2)]);
+        assert_eq!(map.cost_for_movement(&movement), 12);
+        assert_eq!(map.render(&movement), "O...\n.X..\n..O.\n...#\n");
+
+        assert_eq!(
+            Map::parse_terrain("..\n.@\n", Mode::Strict, Terrain::new()).err(),
+            Some(Error::parse("invalid object char `@`").at_column(2).at_line(2))
+        );
+        assert_eq!(Terrain::new().with_char('#', Object::Rock).object('#'), Some(Object::Rock));
+        assert_eq!(Terrain::new().cost(Object::Ice), 0);
+    }
+
+    #[test]
+    fn test_cost_matches_trees() {
+        let map = Day3::parse(&read_file("input/day3.txt").unwrap()).unwrap();
+        assert_eq!(map.cost_for_movement(&Position { x: 3, y: 1 }), 299);
+    }
+
+    #[test]
+    fn test_parse_topology() {
+        assert_eq!("torus".parse(), Ok(Topology::Torus));
+        assert_eq!(
+            "sphere".parse::<Topology>(),
+            Err(Error::parse("unknown topology `sphere`, expected bounded, cylinder, torus or reflecting"))
+        );
+    }
+
+    #[test]
+    fn test_rank_slopes() {
+        let map: Map = EXAMPLE.parse().unwrap();
+        let ranked = map.rank_slopes(1..=7, 1..=2);
+        let trees = |x, y| ranked.iter().find(|(m, _)| *m == Position { x, y }).map(|&(_, t)| t);
+
+        assert_eq!(ranked.len(), 14);
+        assert_eq!((trees(1, 1), trees(3, 1), trees(5, 1), trees(7, 1), trees(1, 2)), (Some(2), Some(7), Some(3), Some(4), Some(2)));
+        assert!(ranked.windows(2).all(|w| w[0].1 <= w[1].1));
+        assert_eq!(map.best_slope(1..=7, 1..=2), Some(ranked[0]));
+        assert_eq!(map.best_slope(0..=0, 0..=0), None);
+    }
+
+    #[test]
+    fn test_rank_slopes_matches_walking() {
+        let map = Day3::parse(&read_file("input/day3.txt").unwrap()).unwrap();
+
+        for topology in [Topology::Bounded, Topology::Cylinder, Topology::Torus, Topology::Reflecting].iter() {
+            let map = map.clone().with_topology(*topology);
+
+            for (movement, trees) in map.rank_slopes(0..=70, 0..=3) {
+                assert_eq!(trees, map.number_of_trees_for_movement(&movement), "{:?} {:?}", topology, movement);
+            }
+        }
+
+        let terrain = Terrain::new().with_char('~', Object::Ice).with_char('o', Object::Rock);
+        let map = Map::parse_terrain("..#.#\n.~..#\n#.#.o\n.#.##\n#...#\n##.~.\n", Mode::Strict, terrain).unwrap();
+
+        for topology in [Topology::Cylinder, Topology::Reflecting].iter() {
+            let map = map.clone().with_topology(*topology);
+
+            for (movement, trees) in map.rank_slopes(0..=12, 0..=3) {
+                assert_eq!(trees, map.number_of_trees_for_movement(&movement), "{:?} {:?}", topology, movement);
+            }
+        }
+    }
+
+    #[test]
+    fn test_column_classes() {
+        let map = Day3::parse(EXAMPLE).unwrap();
+        let mut classes = ColumnClasses::new(&map, 1).unwrap();
+
+        assert_eq!(classes.trees(3), 7);
+        assert_eq!(classes.trees(14), 7);
+        assert_eq!(classes.trees(1), 2);
+        // dx 3 and 1 both visit all 11 columns, so they share the sums
+        assert_eq!(classes.sums.keys().collect::<Vec<_>>(), vec![&11]);
+
+        assert!(ColumnClasses::new(&map, 0).is_none());
+        assert!(ColumnClasses::new(&map.clone().with_topology(Topology::Torus), 1).is_none());
+    }
+
+    #[test]
+    fn test_reflect() {
+        let folded: Vec<usize> = (0..10).map(|x| reflect(x, 4)).collect();
+        assert_eq!(folded, vec![0, 1, 2, 3, 2, 1, 0, 1, 2, 3]);
+        assert_eq!(reflect(7, 1), 0);
+    }
+
+    #[test]
+    fn test_wraps_horizontally() {
+        let map = Map::try_from(vec!["..#".to_string(), "#..".to_string(), ".#.".to_string()]).unwrap();
+
+        assert_eq!(map.get_object_at_pos(&Position { x: 5, y: 0 }), Some(&Object::Tree));
+        assert_eq!(map.get_object_at_pos(&Position { x: 3, y: 1 }), Some(&Object::Tree));
+        assert_eq!(map.get_object_at_pos(&Position { x: 0, y: 3 }), None);
+        assert_eq!(map.number_of_trees_for_movement(&Position { x: 1, y: 1 }), 0);
+        assert_eq!(map.number_of_trees_for_movement(&Position { x: 2, y: 1 }), 1);
     }
 }
@@ -1,130 +1,331 @@
 #![allow(dead_code)]
 
+use std::collections::BTreeMap;
+use std::fmt;
 use std::str::FromStr;
-use std::collections::HashMap;
-use regex::Regex;
+use crate::error::{Error, Result};
+use crate::input::read_records;
+use crate::solution::Solution;
 
-struct Passport {
-    byr: usize,
-    iyr: usize,
-    eyr: usize,
-    hgt: String,
-    hcl: String,
-    ecl: String,
-    pid: String,
-    cid: Option<usize>,
+pub mod generate;
+pub mod record;
+pub mod schema;
+pub mod stats;
+pub mod values;
+
+use record::{tokenize, Diagnostic, Record};
+use schema::{FieldError, Problem, Rule, Schema};
+use values::TypedPassport;
+
+pub struct Day4;
+
+/// How strictly a record is checked against a schema. A malformed token
+/// fails every check; keys the schema doesn't know and keys given twice are
+/// only reported, unless checking `Exact`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Check {
+    /// Only that all required fields are there, as in part one.
+    Presence,
+    /// That all required fields are there and all values follow their rules.
+    Strict,
+    /// Like `Strict`, and that there are no unknown or repeated keys.
+    Exact,
 }
 
-fn parse_num(map: &HashMap<&str, &str>, field: &str) -> Result<usize, &'static str> {
-    map
-        .get(field)
-        .ok_or("failed finding field")
-        .and_then(|&s| s.parse::<usize>().map_err(|_| "failed parsing field to usize"))
+impl Check {
+    fn errors(self, schema: &Schema, fields: &BTreeMap<String, String>) -> Vec<FieldError> {
+        match self {
+            Check::Presence => schema.missing(fields),
+            Check::Strict | Check::Exact => schema.report(fields),
+        }
+    }
+
+    /// Whether a problem with a token makes the record fail this check.
+    fn rejects(self, diagnostic: &Diagnostic) -> bool {
+        match diagnostic {
+            Diagnostic::Malformed { .. } => true,
+            Diagnostic::Duplicate { .. } | Diagnostic::Unknown { .. } => self == Check::Exact,
+        }
+    }
+
+    fn passes(self, schema: &Schema, record: &Record) -> bool {
+        !record.diagnostics.iter().any(|d| self.rejects(d)) && self.errors(schema, &record.fields).is_empty()
+    }
 }
 
-fn parse_string(map: &HashMap<&str, &str>, field: &str) -> Result<String, &'static str> {
-    map
-        .get(field)
-        .map(|&s| s.into())
-        .ok_or("failed parsing field to string")
+/// The fields of a passport that passed its schema, in the order of the
+/// schema, along with the fields of the puzzle in their types when they all
+/// parse.
+#[derive(Debug, PartialEq)]
+pub struct Passport {
+    fields: Vec<(String, String)>,
+    typed: Option<TypedPassport>,
 }
 
-fn validate_year(year: usize, min: usize, max: usize) -> Result<usize, &'static str> {
-    if year >= min && year <= max {
-        Ok(year)
-    } else {
-        Err("invalid year")
+impl Passport {
+    fn from_fields(mut fields: BTreeMap<String, String>, schema: &Schema) -> Passport {
+        let fields = schema
+            .fields()
+            .iter()
+            .filter_map(|field| fields.remove_entry(&field.key))
+            .collect::<Vec<_>>();
+        let typed = TypedPassport::from_fields(&fields).ok();
+
+        Passport { fields, typed }
     }
-}
 
-fn validate_height(s: String) -> Result<String, &'static str> {
-    let re = Regex::new(r"(\d+)([a-z]+)").unwrap();
+    /// Parses a record of whitespace separated `key:value` pairs and checks
+    /// it against `schema`. Malformed tokens are parse errors pointing at the
+    /// token; of a repeated key the first value is kept, and keys the schema
+    /// doesn't know are left out.
+    pub fn parse_with(input: &str, schema: &Schema) -> Result<Passport> {
+        Passport::parse_checked(input, schema, Check::Strict)
+    }
 
-    if !re.is_match(&s) {
-        return Err("bad height")
+    /// Like `parse_with`, checking the record only as far as `check` asks.
+    /// With `Check::Exact` unknown and repeated keys are parse errors too.
+    pub fn parse_checked(input: &str, schema: &Schema, check: Check) -> Result<Passport> {
+        let record = Record::read(input, schema);
+
+        if let Some(d) = record.diagnostics.iter().find(|d| check.rejects(d)) {
+            return Err(d.to_error());
+        }
+
+        match check.errors(schema, &record.fields).into_iter().next() {
+            Some(e) => Err(Error::validation(e.reason)),
+            None => Ok(Passport::from_fields(record.fields, schema)),
+        }
     }
 
-    let matches = re.captures(&s).unwrap();
-    let num: usize = matches.get(1).unwrap().as_str().parse::<usize>().map_err(|_| "cant parse height")?;
-    let unit = matches.get(2).unwrap().as_str();
+    /// Everything wrong with the record, rather than just the first problem:
+    /// problems with its tokens followed by the fields that break `schema`.
+    pub fn report(input: &str, schema: &Schema) -> Vec<FieldError> {
+        let record = Record::read(input, schema);
+
+        record
+            .diagnostics
+            .iter()
+            .map(|d| d.to_field_error())
+            .chain(schema.report(&record.fields))
+            .collect()
+    }
 
-    match unit {
-        "cm" => if num >= 150 && num <= 193 { Ok(s) } else { Err("invalid length") },
-        "in" => if num >= 59 && num <= 76 { Ok(s) } else { Err("invalid length") },
-        _ => Err("bad unit"),
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.fields.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    /// The fields of the puzzle in their types, parsed when the passport
+    /// was made. Always there for a passport that passed a strict check
+    /// against the puzzle schema; `None` when any of them is missing or
+    /// doesn't parse, as can happen after a presence check.
+    pub fn typed(&self) -> Option<&TypedPassport> {
+        self.typed.as_ref()
     }
 }
 
-fn validate_hair_color(s: String) -> Result<String, &'static str> {
-    let re = Regex::new(r"^#[0-9a-f]{6}$").unwrap();
-    if re.is_match(&s.trim()) { Ok(s) } else { Err("invalid hair color") }
+impl fmt::Display for Passport {
+    /// Writes the fields on a single line as space separated `key:value`
+    /// pairs, in the order of the schema.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, (key, value)) in self.fields.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+
+            write!(f, "{}:{}", key, value)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Rewrites a record in the canonical format: a single line of `key:value`
+/// pairs separated by one space, the keys of the schema first in the order
+/// of the schema. Anything else in the record, like unknown keys, repeated
+/// keys and malformed tokens, follows in the order it was found.
+pub fn normalize_record(input: &str, schema: &Schema) -> String {
+    let rank = |key: &str| schema.fields().iter().position(|f| f.key == key).unwrap_or(usize::MAX);
+
+    let mut tokens: Vec<(usize, String)> = tokenize(input)
+        .map(|token| match token {
+            Ok(t) => (rank(t.key), format!("{}:{}", t.key, t.value)),
+            // the key of a malformed token is the token itself
+            Err(d) => (usize::MAX, d.to_field_error().key),
+        })
+        .collect();
+
+    // stable, so repeated and unknown keys keep their order
+    tokens.sort_by_key(|&(rank, _)| rank);
+    tokens.into_iter().map(|(_, token)| token).collect::<Vec<_>>().join(" ")
 }
 
-fn validate_eye_color(s: String) -> Result<String, &'static str> {
-    match s.as_str().trim() {
-        "amb" | "blu" | "brn" | "gry" | "grn" | "hzl" | "oth" => Ok(s),
-        _ => Err("invalid eye color"),
+/// Rewrites a batch of records in the canonical format, see
+/// `normalize_record`, with records separated by a blank line. With
+/// `drop_invalid` records that don't pass that check are left out.
+pub fn normalize<S: AsRef<str>>(entries: &[S], schema: &Schema, drop_invalid: Option<Check>) -> String {
+    let mut out = String::new();
+
+    for entry in entries.iter().map(|e| e.as_ref()) {
+        if let Some(check) = drop_invalid {
+            if Passport::parse_checked(entry, schema, check).is_err() {
+                continue;
+            }
+        }
+
+        if !out.is_empty() {
+            out.push('\n');
+        }
+
+        out.push_str(&normalize_record(entry, schema));
+        out.push('\n');
     }
+
+    out
+}
+
+fn passport_rule(key: &str) -> &'static Rule {
+    &Schema::passport().field(key).expect("the passport schema has the field").rule
 }
 
-fn validate_pid(s: String) -> Result<String, &'static str> {
-    let re = Regex::new(r"^\d{9}$").unwrap();
-    if re.is_match(&s.trim()) { Ok(s) } else { Err("invalid pid") }
+fn validate_year(year: usize, min: usize, max: usize) -> Result<usize> {
+    Rule::Year(min..=max).check(&year.to_string()).map(|_| year)
+}
+
+fn validate_height(s: String) -> Result<String> {
+    passport_rule("hgt").check(&s).map(|_| s)
+}
+
+fn validate_hair_color(s: String) -> Result<String> {
+    passport_rule("hcl").check(&s).map(|_| s)
+}
+
+fn validate_eye_color(s: String) -> Result<String> {
+    passport_rule("ecl").check(&s).map(|_| s)
+}
+
+fn validate_pid(s: String) -> Result<String> {
+    passport_rule("pid").check(&s).map(|_| s)
 }
 
 impl FromStr for Passport {
-    type Err = &'static str;
-
-    fn from_str(input: &str) -> Result<Passport, Self::Err> {
-        let fields: HashMap<&str, &str> = input
-            .split("\n")
-            .map(|line| line.split(" "))
-            .flatten()
-            .filter_map(|line| {
-                let parts = line.split(":").collect::<Vec<_>>();
-                let key = parts.get(0)?;
-                let field = parts.get(1)?;
-                Some((key.to_owned(), field.to_owned()))
-            })
-            .collect();
+    type Err = Error;
 
-        if fields.len() < 7 {
-            return Err("not enough fields");
-        }
+    /// Parses a passport following the rules of the puzzle.
+    fn from_str(input: &str) -> Result<Passport> {
+        Passport::parse_with(input, Schema::passport())
+    }
+}
+
+/// A record that didn't make it into a batch, with everything wrong with it.
+#[derive(Debug, PartialEq)]
+pub struct Rejected {
+    /// The (1-based) position of the record in the batch.
+    pub record: usize,
+    pub errors: Vec<FieldError>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Batch {
+    pub valid: Vec<Passport>,
+    pub rejected: Vec<Rejected>,
+}
+
+/// Sorts a batch of records into passports and rejected records. The errors
+/// of a rejected record include the problems with its tokens that `check`
+/// only reports.
+pub fn check_batch<S: AsRef<str>>(entries: &[S], schema: &Schema, check: Check) -> Batch {
+    let mut batch = Batch::default();
+
+    for (i, entry) in entries.iter().enumerate() {
+        let record = Record::read(entry.as_ref(), schema);
 
-        let passport = Passport {
-            byr: parse_num(&fields, "byr").and_then(|s| validate_year(s, 1920, 2002))?,
-            iyr: parse_num(&fields, "iyr").and_then(|s| validate_year(s, 2010, 2020))?,
-            eyr: parse_num(&fields, "eyr").and_then(|s| validate_year(s, 2020, 2030))?,
-            hgt: parse_string(&fields, "hgt").and_then(|s| validate_height(s))?,
-            hcl: parse_string(&fields, "hcl").and_then(|s| validate_hair_color(s))?,
-            ecl: parse_string(&fields, "ecl").and_then(|s| validate_eye_color(s))?,
-            pid: parse_string(&fields, "pid").and_then(|s| validate_pid(s))?,
-            cid: parse_num(&fields, "cid").ok(),
-        };
+        if check.passes(schema, &record) {
+            batch.valid.push(Passport::from_fields(record.fields, schema));
+        } else {
+            let errors: Vec<FieldError> = record
+                .diagnostics
+                .iter()
+                .map(|d| d.to_field_error())
+                .chain(check.errors(schema, &record.fields))
+                .collect();
 
-        Ok(passport)
+            batch.rejected.push(Rejected { record: i + 1, errors });
+        }
     }
+
+    batch
 }
 
-fn validate(entries: &Vec<&str>) -> Result<usize, &'static str> {
-    let mut passports: Vec<Passport> = vec![];
+/// How a batch of records fared against a schema.
+#[derive(Debug, Default, PartialEq)]
+pub struct Summary {
+    pub checked: usize,
+    pub valid: usize,
+    /// Number of records failing each field.
+    pub by_field: BTreeMap<String, usize>,
+    /// Number of field failures of each kind, e.g. `missing`.
+    pub by_problem: BTreeMap<String, usize>,
+}
+
+pub fn summarize<S: AsRef<str>>(entries: &[S], schema: &Schema) -> Summary {
+    let mut summary = Summary::default();
 
     for entry in entries.iter() {
-        match entry.parse() {
-            Ok(p) => passports.push(p),
-            _ => (),
+        let errors = Passport::report(entry.as_ref(), schema);
+
+        summary.checked += 1;
+
+        // unknown and repeated keys don't fail a strict check
+        if errors.iter().all(|e| matches!(e.problem, Problem::Duplicate | Problem::Unknown)) {
+            summary.valid += 1;
+        }
+
+        for error in errors {
+            *summary.by_field.entry(error.key).or_insert(0) += 1;
+            *summary.by_problem.entry(error.problem.to_string()).or_insert(0) += 1;
         }
     }
 
-    Ok(passports.len())
+    summary
+}
+
+pub fn count_valid<S: AsRef<str>>(entries: &[S], schema: &Schema, check: Check) -> usize {
+    entries
+        .iter()
+        .filter(|entry| check.passes(schema, &Record::read(entry.as_ref(), schema)))
+        .count()
+}
+
+fn validate<S: AsRef<str>>(entries: &[S]) -> usize {
+    count_valid(entries, Schema::passport(), Check::Strict)
+}
+
+impl Solution for Day4 {
+    const DAY: u8 = 4;
+
+    type Input = Vec<String>;
+    type Answer = usize;
+
+    fn parse(input: &str) -> Result<Vec<String>> {
+        read_records(input.as_bytes())
+    }
+
+    fn part_one(entries: &Vec<String>) -> Result<usize> {
+        Ok(count_valid(entries, Schema::passport(), Check::Presence))
+    }
+
+    fn part_two(entries: &Vec<String>) -> Result<usize> {
+        Ok(validate(entries))
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::input::{read_file};
+    use crate::input::{open, read_file};
     use super::*;
 
+    use proptest::prelude::*;
+
     #[test]
     fn test_passport_from_string() {
         let p1 = "ecl:gry pid:860033327 eyr:2020
@@ -138,44 +339,309 @@ mod tests {
         let p5 = "eyr:1972 cid:100 hcl:#18171d ecl:amb hgt:170
             pid:186cm iyr:2018 byr:1926".parse::<Passport>();
 
-        assert_eq!(p1.is_ok(), true);
-        assert_eq!(p2.is_ok(), false);
-        assert_eq!(p3.is_ok(), true);
-        assert_eq!(p4.is_ok(), false);
-        assert_eq!(p5.is_ok(), false);
+        assert!(p1.is_ok());
+        assert!(p2.is_err());
+        assert!(p3.is_ok());
+        assert!(p4.is_err());
+        assert!(p5.is_err());
+
+        assert_eq!(p2.err(), Some(Error::validation("missing field `hgt`")));
+        assert_eq!(p5.err(), Some(Error::validation("year 1972 is not within 2020-2030")));
     }
 
     #[test]
     fn validations() {
-        assert_eq!(validate_year(2002, 1920, 2002).is_ok(), true);
-        assert_eq!(validate_year(2003, 1920, 2002).is_ok(), false);
-        assert_eq!(validate_year(1920, 1920, 2002).is_ok(), true);
-        assert_eq!(validate_year(1919, 1920, 2002).is_ok(), false);
+        assert!(validate_year(2002, 1920, 2002).is_ok());
+        assert!(validate_year(2003, 1920, 2002).is_err());
+        assert!(validate_year(1920, 1920, 2002).is_ok());
+        assert!(validate_year(1919, 1920, 2002).is_err());
 
-        assert_eq!(validate_height("60in".into()).is_ok(), true);
-        assert_eq!(validate_height("190cm".into()).is_ok(), true);
-        assert_eq!(validate_height("150cm".into()).is_ok(), true);
-        assert_eq!(validate_height("190in".into()).is_ok(), false);
+        assert!(validate_height("60in".into()).is_ok());
+        assert!(validate_height("190cm".into()).is_ok());
+        assert!(validate_height("150cm".into()).is_ok());
+        assert!(validate_height("190in".into()).is_err());
+        assert!(validate_height("abc190cmxyz".into()).is_err());
+        assert!(validate_height("190cmxyz".into()).is_err());
 
-        assert_eq!(validate_hair_color("#123abc".into()).is_ok(), true);
-        assert_eq!(validate_hair_color("#aabb99".into()).is_ok(), true);
-        assert_eq!(validate_hair_color("#123abz".into()).is_ok(), false);
+        assert!(validate_hair_color("#123abc".into()).is_ok());
+        assert!(validate_hair_color("#aabb99".into()).is_ok());
+        assert!(validate_hair_color("#123abz".into()).is_err());
 
-        assert_eq!(validate_eye_color("brn".into()).is_ok(), true);
-        assert_eq!(validate_eye_color("grn".into()).is_ok(), true);
-        assert_eq!(validate_eye_color("wat".into()).is_ok(), false);
+        assert!(validate_eye_color("brn".into()).is_ok());
+        assert!(validate_eye_color("grn".into()).is_ok());
+        assert!(validate_eye_color("wat".into()).is_err());
 
-        assert_eq!(validate_pid("000000001".into()).is_ok(), true);
-        assert_eq!(validate_pid("0123456789".into()).is_ok(), false);
+        assert!(validate_pid("000000001".into()).is_ok());
+        assert!(validate_pid("0123456789".into()).is_err());
+        assert!(validate_pid("x000000001".into()).is_err());
+        assert!(validate_hair_color("#123abc0".into()).is_err());
     }
 
     #[test]
     fn test_product() {
-        let input = read_file("input/day4.txt");
-        let entries: Vec<&str> = input
-            .split("\n\n")
+        let entries = read_records(open("input/day4.txt").unwrap()).unwrap();
+
+        assert_eq!(validate(&entries), 184);
+    }
+
+    #[test]
+    fn test_presence() {
+        let schema = Schema::passport();
+        let present = |s: &str| Passport::parse_checked(s, schema, Check::Presence);
+
+        // seven fields, but `cid` is one of them and `pid` is missing
+        assert_eq!(
+            present("byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry cid:147"),
+            Err(Error::validation("missing field `pid`"))
+        );
+        // all but `cid` is fine, `cid` is optional
+        assert!(present("byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry pid:860033327").is_ok());
+        assert!(present("byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry pid:860033327 cid:").is_ok());
+        // values aren't looked at
+        assert!(present("byr:1 iyr:x eyr:2 hgt:3 hcl:y ecl:wat pid:z cid:147").is_ok());
+        assert!(Passport::parse_checked("byr:1 iyr:x eyr:2 hgt:3 hcl:y ecl:wat pid:z cid:147", schema, Check::Strict).is_err());
+        assert_eq!(present("cid:147"), Err(Error::validation("missing field `byr`")));
+
+        let entries = vec![
+            "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd\nbyr:1937 iyr:2017 cid:147 hgt:183cm",
+            "iyr:2013 ecl:amb cid:350 eyr:2023 pid:028048884\nhcl:#cfa07d byr:1929",
+            "hcl:#ae17e1 iyr:2013\neyr:2024\necl:brn pid:760753108 byr:1931\nhgt:179cm",
+            "hcl:#cfa07d eyr:2025 pid:166559648\niyr:2011 ecl:brn hgt:59in",
+        ];
+        assert_eq!(count_valid(&entries, schema, Check::Presence), 2);
+    }
+
+    #[test]
+    fn test_part_one() {
+        let entries = Day4::parse(&read_file("input/day4.txt").unwrap()).unwrap();
+        assert_eq!(Day4::part_one(&entries), Ok(254));
+    }
+
+    #[test]
+    fn test_messy_records() {
+        let passport: Passport = "ecl:gry\tpid:860033327   eyr:2020\r\nhcl:#fffffd byr:1937\r\n  iyr:2017 cid:147 hgt:183cm\r\n"
+            .parse()
+            .unwrap();
+        assert_eq!(passport.get("hgt"), Some("183cm"));
+
+        let valid = "byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry pid:860033327";
+        assert_eq!(
+            format!("{} cid:1:2", valid).parse::<Passport>().map(|p| p.get("cid").map(|c| c.to_string())),
+            Ok(Some("1:2".to_string()))
+        );
+        assert_eq!(format!("{}\nbyr:1940", valid).parse::<Passport>().map(|p| p.get("byr").map(|b| b.to_string())), Ok(Some("1937".to_string())));
+        assert_eq!(
+            Passport::parse_checked(&format!("{}\nbyr:1940", valid), Schema::passport(), Check::Exact),
+            Err(Error::parse("duplicate key `byr`").in_field("byr").at_line(2).at_column(1))
+        );
+        assert_eq!(
+            format!("{} xyz", valid).parse::<Passport>(),
+            Err(Error::parse("malformed token `xyz`, expected `key:value`").at_line(1).at_column(72))
+        );
+
+        let problems: Vec<String> = Passport::report(&format!("{} ecl:blu foo:1 bar", valid), Schema::passport())
+            .iter()
+            .map(|e| e.problem.to_string())
             .collect();
+        assert_eq!(problems, vec!["duplicate", "unknown", "malformed"]);
+    }
+
+    #[test]
+    fn test_unknown_and_repeated_keys() {
+        let complete = "byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry pid:860033327";
+        let entries = vec![format!("{} foo:bar", complete), format!("{}\nbyr:1940", complete)];
+        let schema = Schema::passport();
+
+        assert_eq!(Day4::part_one(&entries), Ok(2));
+        assert_eq!(Day4::part_two(&entries), Ok(2));
+        assert_eq!(count_valid(&entries, schema, Check::Exact), 0);
+
+        let batch = check_batch(&entries, schema, Check::Exact);
+        let problems: Vec<Vec<String>> = batch
+            .rejected
+            .iter()
+            .map(|r| r.errors.iter().map(|e| e.reason.clone()).collect())
+            .collect();
+        assert_eq!(problems, vec![vec!["unknown key `foo`".to_string()], vec!["duplicate key `byr`".to_string()]]);
+        assert_eq!(check_batch(&entries, schema, Check::Presence).valid.len(), 2);
+
+        let summary = summarize(&entries, schema);
+        assert_eq!((summary.valid, summary.by_problem.get("unknown"), summary.by_problem.get("duplicate")), (2, Some(&1), Some(&1)));
+    }
+
+    #[test]
+    fn test_report() {
+        let errors = Passport::report("eyr:1972 cid:100 hcl:#18171d ecl:amb hgt:170\npid:186cm iyr:2018", Schema::passport());
+        let found: Vec<(&str, Option<&str>, &str)> = errors
+            .iter()
+            .map(|e| (e.key.as_str(), e.value.as_deref(), e.reason.as_str()))
+            .collect();
+
+        assert_eq!(found, vec![
+            ("byr", None, "missing field `byr`"),
+            ("eyr", Some("1972"), "year 1972 is not within 2020-2030"),
+            ("hgt", Some("170"), "`170` has no unit, expected cm or in"),
+            ("pid", Some("186cm"), "`186cm` does not match `[0-9]{9}`"),
+        ]);
+    }
+
+    #[test]
+    fn test_summarize() {
+        let entries = vec![
+            "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd byr:1937 iyr:2017 cid:147 hgt:183cm",
+            "iyr:2013 ecl:amb cid:350 eyr:2023 pid:028048884 hcl:#cfa07d byr:1929",
+            "hcl:#cfa07d eyr:2025 pid:166559648 iyr:2011 ecl:brn hgt:59in",
+            "eyr:1972 cid:100 hcl:#18171d ecl:amb hgt:170 pid:186cm iyr:2018 byr:1926",
+        ];
+        let summary = summarize(&entries, Schema::passport());
+        let counts = |pairs: &[(&str, usize)]| pairs.iter().map(|(k, n)| (k.to_string(), *n)).collect::<BTreeMap<_, _>>();
+
+        assert_eq!(summary, Summary {
+            checked: 4,
+            valid: 1,
+            by_field: counts(&[("byr", 1), ("eyr", 1), ("hgt", 2), ("pid", 1)]),
+            by_problem: counts(&[("missing", 2), ("out of range", 1), ("bad unit", 1), ("no match", 1)]),
+        });
+
+        let entries = read_records(open("input/day4.txt").unwrap()).unwrap();
+        let summary = summarize(&entries, Schema::passport());
+        assert_eq!((summary.checked, summary.valid), (entries.len(), 184));
+    }
+
+    #[test]
+    fn test_check_batch() {
+        let entries = vec![
+            "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd byr:1937 iyr:2017 cid:147 hgt:183cm",
+            "iyr:2013 ecl:amb cid:350 eyr:2023 pid:028048884 hcl:#cfa07d byr:1929",
+            "hcl:#ae17e1 iyr:2013 eyr:2024 ecl:brn pid:760753108 byr:1931 hgt:179cm",
+        ];
+        let batch = check_batch(&entries, Schema::passport(), Check::Strict);
+
+        assert_eq!(batch.valid.iter().map(|p| p.get("pid")).collect::<Vec<_>>(), vec![Some("860033327"), Some("760753108")]);
+        assert_eq!(batch.rejected.len(), 1);
+        assert_eq!(batch.rejected[0].record, 2);
+        assert_eq!(batch.rejected[0].errors[0].reason, "missing field `hgt`");
+
+        let entries = read_records(open("input/day4.txt").unwrap()).unwrap();
+        let batch = check_batch(&entries, Schema::passport(), Check::Presence);
+        assert_eq!((batch.valid.len(), batch.rejected.len()), (254, entries.len() - 254));
+    }
+
+    #[test]
+    fn test_display() {
+        let passport: Passport = "hcl:#fffffd byr:1937\niyr:2017 cid:147 hgt:183cm ecl:gry pid:000033327 eyr:2020".parse().unwrap();
+
+        assert_eq!(passport.to_string(), "byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry pid:000033327 cid:147");
+        assert_eq!(passport.to_string().parse(), Ok(passport));
+    }
+
+    #[test]
+    fn test_round_trip_file() {
+        let entries = read_records(open("input/day4.txt").unwrap()).unwrap();
+        let batch = check_batch(&entries, Schema::passport(), Check::Presence);
+
+        for passport in batch.valid.iter() {
+            let schema = Schema::passport();
+            assert_eq!(Passport::parse_checked(&passport.to_string(), schema, Check::Presence).as_ref(), Ok(passport));
+        }
+    }
+
+    #[test]
+    fn test_normalize() {
+        let schema = Schema::passport();
+
+        assert_eq!(
+            normalize_record("pid:1\t\tfoo:bar  byr:1937\r\n cid:1:2 byr:1940 junk", schema),
+            "byr:1937 byr:1940 pid:1 cid:1:2 foo:bar junk"
+        );
+
+        let entries = vec![
+            "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd\nbyr:1937 iyr:2017 cid:147 hgt:183cm",
+            "iyr:2013 ecl:amb cid:350 eyr:2023 pid:028048884\nhcl:#cfa07d byr:1929",
+        ];
+        assert_eq!(normalize(&entries, schema, None), "\
+byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry pid:860033327 cid:147
+
+byr:1929 iyr:2013 eyr:2023 hcl:#cfa07d ecl:amb pid:028048884 cid:350
+");
+        assert_eq!(
+            normalize(&entries, schema, Some(Check::Strict)),
+            "byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry pid:860033327 cid:147\n"
+        );
+
+        // normalizing is idempotent
+        let once = normalize(&entries, schema, None);
+        let records = read_records(once.as_bytes()).unwrap();
+        assert_eq!(normalize(&records, schema, None), once);
+    }
+
+    proptest! {
+        #[test]
+        fn display_round_trips(
+            fields in prop::collection::btree_map(
+                prop::sample::select(vec!["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid", "cid"]),
+                "[!-~]{0,12}",
+                0..8,
+            ),
+        ) {
+            let schema: Schema = "byr optional any\niyr optional any\neyr optional any\nhgt optional any\nhcl optional any\necl optional any\npid optional any\ncid optional any\n"
+                .parse()
+                .unwrap();
+            let record: Vec<String> = fields.iter().map(|(k, v)| format!("{}:{}", k, v)).collect();
+            let passport = Passport::parse_with(&record.join("\n"), &schema).unwrap();
+
+            prop_assert_eq!(Passport::parse_with(&passport.to_string(), &schema), Ok(passport));
+        }
+    }
+
+    // cargo test --release bench_million_records -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn bench_million_records() {
+        use std::time::Instant;
+        use crate::day4::generate::{to_batch, Generator};
+
+        let samples = Generator::new(2020).batch(1_000_000);
+        let batch = to_batch(&samples);
+        let entries = read_records(batch.as_bytes()).unwrap();
+
+        for check in [Check::Presence, Check::Strict].iter() {
+            let start = Instant::now();
+            let valid = count_valid(&entries, Schema::passport(), *check);
+            let elapsed = start.elapsed();
+
+            println!(
+                "{:?}: {} of {} valid in {:.2?}, {:.0} records/s",
+                check,
+                valid,
+                entries.len(),
+                elapsed,
+                entries.len() as f64 / elapsed.as_secs_f64()
+            );
+        }
+
+        let start = Instant::now();
+        let parsed = entries.iter().filter(|e| e.parse::<Passport>().is_ok()).count();
+        println!("FromStr: {} parsed in {:.2?}", parsed, start.elapsed());
+        assert_eq!(parsed, samples.iter().filter(|s| s.is_valid()).count());
+    }
+
+    #[test]
+    fn test_custom_schema() {
+        let schema: Schema = "id required regex [A-Z]{2}[0-9]{4}\nage optional int 18-120\n".parse().unwrap();
+
+        let passport = Passport::parse_with("id:NL1234 age:40", &schema).unwrap();
+        assert_eq!((passport.get("id"), passport.get("age")), (Some("NL1234"), Some("40")));
+        assert_eq!(Passport::parse_with("id:NL1234 age:40\nbyr:1900", &schema).map(|p| p.to_string()), Ok("id:NL1234 age:40".to_string()));
+        assert_eq!(
+            Passport::parse_checked("id:NL1234 age:40\nbyr:1900", &schema, Check::Exact),
+            Err(Error::parse("unknown key `byr`").in_field("byr").at_line(2).at_column(1))
+        );
 
-        assert_eq!(validate(&entries), Ok(184));
+        assert_eq!(
+            Passport::parse_with("id:NL123 age:40", &schema),
+            Err(Error::validation("`NL123` does not match `[A-Z]{2}[0-9]{4}`"))
+        );
+        assert_eq!(Passport::parse_with("age:17", &schema), Err(Error::validation("missing field `id`")));
     }
 }
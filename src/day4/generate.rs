@@ -0,0 +1,279 @@
+use super::schema::Problem;
+
+const EYE_COLORS: [&str; 7] = ["amb", "blu", "brn", "gry", "grn", "hzl", "oth"];
+const REQUIRED: [&str; 7] = ["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid"];
+
+/// Values just past the edges of the rules of the puzzle, with how they
+/// break their rule.
+const BAD_VALUES: [(&str, &str, Problem); 20] = [
+    ("byr", "1919", Problem::OutOfRange),
+    ("byr", "2003", Problem::OutOfRange),
+    ("byr", "193", Problem::Malformed),
+    ("iyr", "2009", Problem::OutOfRange),
+    ("iyr", "2021", Problem::OutOfRange),
+    ("eyr", "2019", Problem::OutOfRange),
+    ("eyr", "2031", Problem::OutOfRange),
+    ("hgt", "149cm", Problem::OutOfRange),
+    ("hgt", "194cm", Problem::OutOfRange),
+    ("hgt", "58in", Problem::OutOfRange),
+    ("hgt", "77in", Problem::OutOfRange),
+    ("hgt", "170", Problem::BadUnit),
+    ("hgt", "6ft", Problem::BadUnit),
+    ("hcl", "#12345g", Problem::NoMatch),
+    ("hcl", "#12345", Problem::NoMatch),
+    ("hcl", "#1234567", Problem::NoMatch),
+    ("hcl", "123456", Problem::NoMatch),
+    ("ecl", "xyz", Problem::NotAllowed),
+    ("pid", "01234567", Problem::NoMatch),
+    ("pid", "0123456789", Problem::NoMatch),
+];
+
+/// A xorshift generator, good enough for test data and the same on every
+/// platform for the same seed.
+struct Rng(u64);
+
+impl Rng {
+    /// Spreads the seed with splitmix64, so nearby seeds give unrelated
+    /// streams. Xorshift never leaves a zero state, and the one seed that
+    /// mixes to zero starts from a fixed state instead.
+    fn new(seed: u64) -> Rng {
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+
+        Rng(if z == 0 { 0x9e37_79b9_7f4a_7c15 } else { z })
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    /// A number in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// A number in `min..=max`, half of the time one of the bounds.
+    fn edgy(&mut self, min: usize, max: usize) -> usize {
+        match self.below(4) {
+            0 => min,
+            1 => max,
+            _ => min + self.below(max - min + 1),
+        }
+    }
+
+    fn chance(&mut self, share: f64) -> bool {
+        ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < share
+    }
+
+    fn digits(&mut self, n: usize) -> String {
+        (0..n).map(|_| (b'0' + self.below(10) as u8) as char).collect()
+    }
+}
+
+/// The one rule a generated record breaks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Violation {
+    pub key: &'static str,
+    pub problem: Problem,
+}
+
+/// A generated record with the verdict it should get.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub record: String,
+    /// `None` for a valid passport.
+    pub violation: Option<Violation>,
+}
+
+impl Sample {
+    pub fn is_valid(&self) -> bool {
+        self.violation.is_none()
+    }
+
+    /// Whether the record has all required fields, as part one checks.
+    pub fn is_complete(&self) -> bool {
+        self.violation.map_or(true, |v| v.problem != Problem::Missing)
+    }
+}
+
+/// Generates batches of passports for the rules of the puzzle. The same seed
+/// always gives the same batch.
+pub struct Generator {
+    rng: Rng,
+    invalid_share: f64,
+}
+
+impl Generator {
+    pub fn new(seed: u64) -> Generator {
+        Generator { rng: Rng::new(seed), invalid_share: 0.5 }
+    }
+
+    /// The share of records, between 0 and 1, that break a rule.
+    pub fn invalid_share(self, invalid_share: f64) -> Generator {
+        Generator { invalid_share, ..self }
+    }
+
+    fn valid_fields(&mut self) -> Vec<(&'static str, String)> {
+        let rng = &mut self.rng;
+
+        let hgt = if rng.chance(0.5) {
+            format!("{}cm", rng.edgy(150, 193))
+        } else {
+            format!("{}in", rng.edgy(59, 76))
+        };
+        let hcl: String = (0..6).map(|_| b"0123456789abcdef"[rng.below(16)] as char).collect();
+
+        let mut fields = vec![
+            ("byr", rng.edgy(1920, 2002).to_string()),
+            ("iyr", rng.edgy(2010, 2020).to_string()),
+            ("eyr", rng.edgy(2020, 2030).to_string()),
+            ("hgt", hgt),
+            ("hcl", format!("#{}", hcl)),
+            ("ecl", EYE_COLORS[rng.below(EYE_COLORS.len())].to_string()),
+            ("pid", rng.digits(9)),
+        ];
+
+        if rng.chance(0.5) {
+            fields.push(("cid", (1 + rng.below(999)).to_string()));
+        }
+
+        fields
+    }
+
+    pub fn sample(&mut self) -> Sample {
+        let mut fields = self.valid_fields();
+        let mut violation = None;
+
+        if self.rng.chance(self.invalid_share) {
+            let choice = self.rng.below(BAD_VALUES.len() + REQUIRED.len());
+
+            violation = Some(match BAD_VALUES.get(choice) {
+                Some(&(key, value, problem)) => {
+                    let field = fields.iter_mut().find(|(k, _)| *k == key).expect("all fields are generated");
+                    field.1 = value.to_string();
+                    Violation { key, problem }
+                }
+                None => {
+                    let key = REQUIRED[choice - BAD_VALUES.len()];
+                    fields.retain(|(k, _)| *k != key);
+                    Violation { key, problem: Problem::Missing }
+                }
+            });
+        }
+
+        // shuffle the fields and spread them over a few lines
+        for i in (1..fields.len()).rev() {
+            fields.swap(i, self.rng.below(i + 1));
+        }
+
+        let mut record = String::new();
+        for (i, (key, value)) in fields.iter().enumerate() {
+            if i > 0 {
+                record.push(if self.rng.chance(0.25) { '\n' } else { ' ' });
+            }
+
+            record.push_str(key);
+            record.push(':');
+            record.push_str(value);
+        }
+
+        Sample { record, violation }
+    }
+
+    pub fn batch(&mut self, n: usize) -> Vec<Sample> {
+        (0..n).map(|_| self.sample()).collect()
+    }
+}
+
+/// Writes samples out as a batch file, records separated by a blank line.
+pub fn to_batch(samples: &[Sample]) -> String {
+    let records: Vec<&str> = samples.iter().map(|s| s.record.as_str()).collect();
+    records.join("\n\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day4::schema::Schema;
+    use crate::day4::{check_batch, count_valid, Check, Passport};
+    use crate::input::read_records;
+
+    #[test]
+    fn test_deterministic() {
+        assert_eq!(Generator::new(7).batch(50), Generator::new(7).batch(50));
+        assert_ne!(Generator::new(7).batch(50), Generator::new(8).batch(50));
+        assert_ne!(Generator::new(0).batch(5), Generator::new(0).batch(6)[1..].to_vec());
+    }
+
+    #[test]
+    fn test_invalid_share() {
+        assert!(Generator::new(1).invalid_share(0.0).batch(200).iter().all(|s| s.is_valid()));
+        assert!(Generator::new(1).invalid_share(1.0).batch(200).iter().all(|s| !s.is_valid()));
+
+        let invalid = Generator::new(1).invalid_share(0.3).batch(10_000).iter().filter(|s| !s.is_valid()).count();
+        assert!((2_700..3_300).contains(&invalid), "{} invalid records", invalid);
+    }
+
+    #[test]
+    fn test_verdicts() {
+        let schema = Schema::passport();
+
+        for sample in Generator::new(2020).batch(5_000) {
+            let found: Vec<(String, Problem)> = Passport::report(&sample.record, schema)
+                .into_iter()
+                .map(|e| (e.key, e.problem))
+                .collect();
+            let expected: Vec<(String, Problem)> = sample.violation.iter().map(|v| (v.key.to_string(), v.problem)).collect();
+
+            assert_eq!(found, expected, "{}", sample.record);
+            assert_eq!(Passport::parse_checked(&sample.record, schema, Check::Presence).is_ok(), sample.is_complete());
+        }
+    }
+
+    #[test]
+    fn test_batch() {
+        let samples = Generator::new(4).batch(300);
+        let entries = read_records(to_batch(&samples).as_bytes()).unwrap();
+        let schema = Schema::passport();
+
+        assert_eq!(entries.len(), 300);
+        assert_eq!(count_valid(&entries, schema, Check::Strict), samples.iter().filter(|s| s.is_valid()).count());
+        assert_eq!(
+            check_batch(&entries, schema, Check::Presence).valid.len(),
+            samples.iter().filter(|s| s.is_complete()).count()
+        );
+    }
+
+    #[test]
+    fn test_no_stuck_seed() {
+        // the seed that used to start xorshift at zero
+        let samples = Generator::new(0x9e37_79b9_7f4a_7c15).batch(100);
+
+        assert!(samples.iter().any(|s| s.is_valid()));
+        assert!(samples.iter().any(|s| !s.is_valid()));
+        assert!(samples.iter().any(|s| s.record != samples[0].record));
+
+        // and the seed that mixes to zero
+        assert_eq!(Rng::new(0x61c8_8646_80b5_83eb).0, 0x9e37_79b9_7f4a_7c15);
+        assert!((0..100).all(|seed| Rng::new(seed).0 != 0));
+    }
+
+    #[test]
+    fn test_every_violation_occurs() {
+        let samples = Generator::new(3).invalid_share(1.0).batch(2_000);
+
+        for &(key, _, problem) in BAD_VALUES.iter() {
+            assert!(samples.iter().any(|s| s.violation == Some(Violation { key, problem })));
+        }
+        for &key in REQUIRED.iter() {
+            assert!(samples.iter().any(|s| s.violation == Some(Violation { key, problem: Problem::Missing })));
+        }
+    }
+}
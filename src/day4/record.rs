@@ -0,0 +1,188 @@
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::iter;
+
+use crate::error::Error;
+use super::schema::{FieldError, Problem, Schema};
+
+/// A `key:value` token of a record, with the (1-based) line and column it
+/// starts at.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Token<'a> {
+    pub key: &'a str,
+    pub value: &'a str,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Something wrong with a token of a record, as opposed to a value that
+/// breaks its rule.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Diagnostic {
+    /// A token that isn't `key:value`.
+    Malformed { token: String, line: usize, column: usize },
+    /// A key that was given before, the first value is kept.
+    Duplicate { key: String, value: String, line: usize, column: usize },
+    /// A key the schema doesn't declare.
+    Unknown { key: String, value: String, line: usize, column: usize },
+}
+
+impl Diagnostic {
+    fn reason(&self) -> String {
+        match self {
+            Diagnostic::Malformed { token, .. } => format!("malformed token `{}`, expected `key:value`", token),
+            Diagnostic::Duplicate { key, .. } => format!("duplicate key `{}`", key),
+            Diagnostic::Unknown { key, .. } => format!("unknown key `{}`", key),
+        }
+    }
+
+    /// A parse error pointing at the token.
+    pub fn to_error(&self) -> Error {
+        let e = Error::parse(self.reason());
+
+        match self {
+            Diagnostic::Malformed { line, column, .. } => e.at_line(*line).at_column(*column),
+            Diagnostic::Duplicate { key, line, column, .. } | Diagnostic::Unknown { key, line, column, .. } => {
+                e.in_field(key).at_line(*line).at_column(*column)
+            }
+        }
+    }
+
+    pub fn to_field_error(&self) -> FieldError {
+        let (key, value, problem) = match self {
+            Diagnostic::Malformed { token, .. } => (token, None, Problem::Malformed),
+            Diagnostic::Duplicate { key, value, .. } => (key, Some(value.clone()), Problem::Duplicate),
+            Diagnostic::Unknown { key, value, .. } => (key, Some(value.clone()), Problem::Unknown),
+        };
+
+        FieldError { key: key.clone(), value, problem, reason: self.reason() }
+    }
+}
+
+/// The whitespace separated words of a line with their byte offsets.
+fn words(line: &str) -> Vec<(usize, &str)> {
+    let mut words = vec![];
+    let mut start = None;
+
+    for (i, c) in line.char_indices().chain(iter::once((line.len(), ' '))) {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some(i),
+            (Some(s), true) => {
+                words.push((s, &line[s..i]));
+                start = None;
+            }
+            _ => (),
+        }
+    }
+
+    words
+}
+
+/// Splits a record into `key:value` tokens. Tokens are separated by any
+/// whitespace, including tabs, repeated spaces and CRLF line endings; a value
+/// runs up to the next whitespace and may contain `:`.
+pub fn tokenize(input: &str) -> impl Iterator<Item = Result<Token<'_>, Diagnostic>> {
+    input.lines().enumerate().flat_map(|(y, text)| {
+        words(text).into_iter().map(move |(offset, word)| {
+            let line = y + 1;
+            // columns count chars rather than bytes
+            let column = text[..offset].chars().count() + 1;
+
+            match word.find(':') {
+                Some(i) if i > 0 => Ok(Token { key: &word[..i], value: &word[i + 1..], line, column }),
+                _ => Err(Diagnostic::Malformed { token: word.to_string(), line, column }),
+            }
+        })
+    })
+}
+
+/// The fields of a record, with everything wrong with its tokens.
+#[derive(Debug, Default, PartialEq)]
+pub struct Record {
+    pub fields: BTreeMap<String, String>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Record {
+    pub fn read(input: &str, schema: &Schema) -> Record {
+        let mut record = Record::default();
+
+        for token in tokenize(input) {
+            let token = match token {
+                Ok(token) => token,
+                Err(d) => {
+                    record.diagnostics.push(d);
+                    continue;
+                }
+            };
+
+            let (key, value, line, column) = (token.key.to_string(), token.value.to_string(), token.line, token.column);
+
+            if schema.field(&key).is_none() {
+                record.diagnostics.push(Diagnostic::Unknown { key, value, line, column });
+                continue;
+            }
+
+            match record.fields.entry(key) {
+                Entry::Vacant(e) => {
+                    e.insert(value);
+                }
+                Entry::Occupied(e) => {
+                    let key = e.key().clone();
+                    record.diagnostics.push(Diagnostic::Duplicate { key, value, line, column });
+                }
+            }
+        }
+
+        record
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(input: &str) -> Vec<(&str, &str, usize, usize)> {
+        tokenize(input).map(|t| t.map(|t| (t.key, t.value, t.line, t.column)).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(tokens("ecl:gry  pid:1\tcid:2\r\n\t hgt:183cm \r\nurl:http://x:8"), vec![
+            ("ecl", "gry", 1, 1),
+            ("pid", "1", 1, 10),
+            ("cid", "2", 1, 16),
+            ("hgt", "183cm", 2, 3),
+            ("url", "http://x:8", 3, 1),
+        ]);
+        assert_eq!(tokens("cid:"), vec![("cid", "", 1, 1)]);
+        assert_eq!(tokens("ü:é hcl:#fff"), vec![("ü", "é", 1, 1), ("hcl", "#fff", 1, 5)]);
+        assert!(tokens("  \n\r\n").is_empty());
+
+        let malformed: Vec<_> = tokenize("ecl:gry pid\n:123").filter_map(|t| t.err()).collect();
+        assert_eq!(malformed, vec![
+            Diagnostic::Malformed { token: "pid".to_string(), line: 1, column: 9 },
+            Diagnostic::Malformed { token: ":123".to_string(), line: 2, column: 1 },
+        ]);
+    }
+
+    #[test]
+    fn test_read() {
+        let schema: Schema = "ecl optional any\npid optional any\n".parse().unwrap();
+        let record = Record::read("ecl:gry pid:1 ecl:blu\nfoo:bar baz", &schema);
+
+        assert_eq!(record.fields.get("ecl").map(|v| v.as_str()), Some("gry"));
+        assert_eq!(record.fields.len(), 2);
+        assert_eq!(record.diagnostics.iter().map(|d| d.to_error().to_string()).collect::<Vec<_>>(), vec![
+            "parse error at line 1, column 15 in `ecl`: duplicate key `ecl`",
+            "parse error at line 2, column 1 in `foo`: unknown key `foo`",
+            "parse error at line 2, column 9: malformed token `baz`, expected `key:value`",
+        ]);
+        assert_eq!(record.diagnostics[1].to_field_error(), FieldError {
+            key: "foo".to_string(),
+            value: Some("bar".to_string()),
+            problem: Problem::Unknown,
+            reason: "unknown key `foo`".to_string(),
+        });
+    }
+}
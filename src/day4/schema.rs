@@ -0,0 +1,480 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::error::{Error, Result};
+use crate::input::read_file;
+
+/// The rules of the puzzle, see `passport.schema` for the format.
+pub const PASSPORT: &str = include_str!("passport.schema");
+
+/// What the value of a field has to look like.
+#[derive(Debug, Clone)]
+pub enum Rule {
+    /// Anything goes.
+    Any,
+    /// A four digit year within the range.
+    Year(RangeInclusive<usize>),
+    /// A number within the range.
+    Int(RangeInclusive<usize>),
+    /// The whole value matches the pattern.
+    Pattern(Regex),
+    /// One of a fixed set of values.
+    OneOf(Vec<String>),
+    /// A number directly followed by one of the units, within the range for
+    /// that unit.
+    Measure(Vec<(String, RangeInclusive<usize>)>),
+}
+
+fn within(n: usize, range: &RangeInclusive<usize>) -> bool {
+    range.contains(&n)
+}
+
+/// Splits `150-193cm` into the range and what follows it.
+fn parse_range(s: &str) -> Result<(RangeInclusive<usize>, &str)> {
+    let invalid = || Error::parse(format!("invalid range `{}`, expected `<min>-<max>`", s));
+
+    let (min, rest) = s.split_at(s.find('-').ok_or_else(invalid)?);
+    let rest = &rest[1..];
+    let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+    let (max, suffix) = rest.split_at(digits);
+
+    let min = min.parse().map_err(|_| invalid())?;
+    let max = max.parse().map_err(|_| invalid())?;
+
+    Ok((min..=max, suffix))
+}
+
+/// The kind of problem a value has, for counting problems across documents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Problem {
+    Missing,
+    Malformed,
+    OutOfRange,
+    NoMatch,
+    NotAllowed,
+    BadUnit,
+    Duplicate,
+    Unknown,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Problem::Missing => "missing",
+            Problem::Malformed => "malformed",
+            Problem::OutOfRange => "out of range",
+            Problem::NoMatch => "no match",
+            Problem::NotAllowed => "not allowed",
+            Problem::BadUnit => "bad unit",
+            Problem::Duplicate => "duplicate",
+            Problem::Unknown => "unknown",
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+impl Rule {
+    /// Returns a validation error saying why `value` breaks the rule.
+    pub fn check(&self, value: &str) -> Result<()> {
+        match self.violation(value) {
+            Some((_, reason)) => Err(Error::validation(reason)),
+            None => Ok(()),
+        }
+    }
+
+    /// What is wrong with `value`, if anything.
+    pub fn violation(&self, value: &str) -> Option<(Problem, String)> {
+        match self {
+            Rule::Any => None,
+            Rule::Year(range) => {
+                let year = match value.parse::<usize>() {
+                    Ok(year) if value.len() == 4 => year,
+                    _ => return Some((Problem::Malformed, format!("year `{}` is not a four digit number", value))),
+                };
+
+                if within(year, range) {
+                    None
+                } else {
+                    Some((Problem::OutOfRange, format!("year {} is not within {}-{}", year, range.start(), range.end())))
+                }
+            }
+            Rule::Int(range) => {
+                let n = match value.parse::<usize>() {
+                    Ok(n) => n,
+                    Err(_) => return Some((Problem::Malformed, format!("`{}` is not a number", value))),
+                };
+
+                if within(n, range) {
+                    None
+                } else {
+                    Some((Problem::OutOfRange, format!("{} is not within {}-{}", n, range.start(), range.end())))
+                }
+            }
+            Rule::Pattern(re) => {
+                if re.is_match(value) {
+                    None
+                } else {
+                    // the pattern is stored anchored, show it as written
+                    let pattern = re.as_str().trim_start_matches("^(?:").trim_end_matches(")$");
+                    Some((Problem::NoMatch, format!("`{}` does not match `{}`", value, pattern)))
+                }
+            }
+            Rule::OneOf(options) => {
+                if options.iter().any(|o| o == value) {
+                    None
+                } else {
+                    Some((Problem::NotAllowed, format!("`{}` is not one of {}", value, options.join(", "))))
+                }
+            }
+            Rule::Measure(units) => {
+                let digits = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+                let (n, unit) = value.split_at(digits);
+                let names = units.iter().map(|(u, _)| u.as_str()).collect::<Vec<_>>().join(" or ");
+
+                let n = match n.parse::<usize>() {
+                    Ok(n) => n,
+                    Err(_) => return Some((Problem::Malformed, format!("`{}` does not start with a number", value))),
+                };
+                let range = match units.iter().find(|(u, _)| u == unit) {
+                    Some((_, range)) => range,
+                    None if unit.is_empty() => {
+                        return Some((Problem::BadUnit, format!("`{}` has no unit, expected {}", value, names)));
+                    }
+                    None => {
+                        let reason = format!("`{}` has an unknown unit `{}`, expected {}", value, unit, names);
+                        return Some((Problem::BadUnit, reason));
+                    }
+                };
+
+                if within(n, range) {
+                    None
+                } else {
+                    Some((Problem::OutOfRange, format!("`{}` is not within {}-{}{}", value, range.start(), range.end(), unit)))
+                }
+            }
+        }
+    }
+}
+
+/// A field of a document that doesn't satisfy the schema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldError {
+    pub key: String,
+    /// The value as found in the document, `None` when it is missing.
+    pub value: Option<String>,
+    pub problem: Problem,
+    pub reason: String,
+}
+
+impl FromStr for Rule {
+    type Err = Error;
+
+    /// Parses a rule name followed by its arguments, e.g. `year 1920-2002`.
+    fn from_str(s: &str) -> Result<Rule> {
+        let s = s.trim();
+        let (name, args) = match s.find(char::is_whitespace) {
+            Some(i) => (&s[..i], s[i..].trim()),
+            None => (s, ""),
+        };
+
+        let range = |args: &str| -> Result<RangeInclusive<usize>> {
+            match parse_range(args)? {
+                (range, "") => Ok(range),
+                _ => Err(Error::parse(format!("invalid range `{}`, expected `<min>-<max>`", args))),
+            }
+        };
+
+        let rule = match name {
+            "any" => Rule::Any,
+            "year" => Rule::Year(range(args)?),
+            "int" => Rule::Int(range(args)?),
+            "regex" => {
+                if args.is_empty() {
+                    return Err(Error::parse("`regex` needs a pattern"));
+                }
+
+                let re = Regex::new(&format!("^(?:{})$", args))
+                    .map_err(|e| Error::parse(format!("invalid pattern `{}`: {}", args, e)))?;
+                Rule::Pattern(re)
+            }
+            "one-of" => {
+                let options: Vec<String> = args.split_whitespace().map(|o| o.to_string()).collect();
+
+                if options.is_empty() {
+                    return Err(Error::parse("`one-of` needs at least one value"));
+                }
+
+                Rule::OneOf(options)
+            }
+            "measure" => {
+                let units = args
+                    .split_whitespace()
+                    .map(|arg| match parse_range(arg)? {
+                        (_, "") => Err(Error::parse(format!("range `{}` has no unit", arg))),
+                        (range, unit) => Ok((unit.to_string(), range)),
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+
+                if units.is_empty() {
+                    return Err(Error::parse("`measure` needs at least one unit range"));
+                }
+
+                Rule::Measure(units)
+            }
+            _ => {
+                return Err(Error::parse(format!(
+                    "unknown rule `{}`, expected any, year, int, regex, one-of or measure",
+                    name
+                )))
+            }
+        };
+
+        Ok(rule)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Field {
+    pub key: String,
+    pub required: bool,
+    pub rule: Rule,
+}
+
+/// The fields a document can have. Fields are checked in the order they are
+/// declared in.
+#[derive(Debug, Clone)]
+pub struct Schema {
+    fields: Vec<Field>,
+}
+
+impl Schema {
+    /// The schema of the puzzle, parsed and compiled on first use and shared
+    /// after that.
+    pub fn passport() -> &'static Schema {
+        static PASSPORT_SCHEMA: OnceLock<Schema> = OnceLock::new();
+        PASSPORT_SCHEMA.get_or_init(|| PASSPORT.parse().expect("the passport schema is valid"))
+    }
+
+    pub fn load(path: &str) -> Result<Schema> {
+        read_file(path)?.parse()
+    }
+
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    pub fn field(&self, key: &str) -> Option<&Field> {
+        self.fields.iter().find(|f| f.key == key)
+    }
+
+    /// Checks the fields of a document, returning the first problem found:
+    /// a required field that is missing or a value that breaks its rule.
+    /// Keys the schema doesn't know about are ignored.
+    pub fn check(&self, values: &BTreeMap<String, String>) -> Result<()> {
+        match self.report(values).into_iter().next() {
+            Some(e) => Err(Error::validation(e.reason)),
+            None => Ok(()),
+        }
+    }
+
+    /// The required fields a document doesn't have, whatever the values of
+    /// the fields it does have.
+    pub fn missing(&self, values: &BTreeMap<String, String>) -> Vec<FieldError> {
+        self.fields
+            .iter()
+            .filter(|field| field.required && !values.contains_key(&field.key))
+            .map(|field| FieldError {
+                key: field.key.clone(),
+                value: None,
+                problem: Problem::Missing,
+                reason: format!("missing field `{}`", field.key),
+            })
+            .collect()
+    }
+
+    /// Every field of a document that doesn't satisfy the schema, in the
+    /// order of the schema.
+    pub fn report(&self, values: &BTreeMap<String, String>) -> Vec<FieldError> {
+        let mut errors = vec![];
+
+        for field in self.fields.iter() {
+            let value = values.get(&field.key);
+            let violation = match value {
+                Some(value) => field.rule.violation(value),
+                None if field.required => Some((Problem::Missing, format!("missing field `{}`", field.key))),
+                None => None,
+            };
+
+            if let Some((problem, reason)) = violation {
+                errors.push(FieldError { key: field.key.clone(), value: value.cloned(), problem, reason });
+            }
+        }
+
+        errors
+    }
+}
+
+impl FromStr for Schema {
+    type Err = Error;
+
+    /// One field per line: `<key> <required|optional> <rule> [arguments]`.
+    /// Blank lines and lines starting with `#` are skipped.
+    fn from_str(input: &str) -> Result<Schema> {
+        let mut fields: Vec<Field> = vec![];
+
+        for (i, line) in input.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.splitn(3, char::is_whitespace).map(str::trim);
+            let key = parts.next().unwrap_or("");
+            let required = match parts.next() {
+                Some("required") => true,
+                Some("optional") => false,
+                other => {
+                    let e = Error::parse(format!("expected `required` or `optional`, got `{}`", other.unwrap_or("")));
+                    return Err(e.in_field(key).at_line(i + 1));
+                }
+            };
+            let rule = parts
+                .next()
+                .unwrap_or("")
+                .parse()
+                .map_err(|e: Error| e.in_field(key).at_line(i + 1))?;
+
+            if fields.iter().any(|f| f.key == key) {
+                return Err(Error::parse(format!("field `{}` is declared twice", key)).at_line(i + 1));
+            }
+
+            fields.push(Field { key: key.to_string(), required, rule });
+        }
+
+        Ok(Schema { fields })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(s: &str) -> Rule {
+        s.parse().unwrap()
+    }
+
+    fn values(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_rules() {
+        assert_eq!(rule("any").check(""), Ok(()));
+
+        let year = rule("year 1920-2002");
+        assert_eq!(year.check("2002"), Ok(()));
+        assert_eq!(year.check("2003"), Err(Error::validation("year 2003 is not within 1920-2002")));
+        assert_eq!(year.check("02002"), Err(Error::validation("year `02002` is not a four digit number")));
+
+        let int = rule("int 1-10");
+        assert_eq!(int.check("10"), Ok(()));
+        assert_eq!(int.check("ten"), Err(Error::validation("`ten` is not a number")));
+        assert_eq!(int.check("11"), Err(Error::validation("11 is not within 1-10")));
+
+        let pattern = rule("regex #[0-9a-f]{6}");
+        assert_eq!(pattern.check("#a0b1c2"), Ok(()));
+        assert_eq!(pattern.check("x#a0b1c2"), Err(Error::validation("`x#a0b1c2` does not match `#[0-9a-f]{6}`")));
+        assert!(pattern.check("#a0b1c23").is_err());
+
+        let one_of = rule("one-of amb  blu");
+        assert_eq!(one_of.check("blu"), Ok(()));
+        assert_eq!(one_of.check("bl"), Err(Error::validation("`bl` is not one of amb, blu")));
+
+        let measure = rule("measure 150-193cm 59-76in");
+        assert_eq!(measure.check("150cm"), Ok(()));
+        assert_eq!(measure.check("76in"), Ok(()));
+        assert_eq!(measure.check("77in"), Err(Error::validation("`77in` is not within 59-76in")));
+        assert_eq!(measure.check("170"), Err(Error::validation("`170` has no unit, expected cm or in")));
+        assert_eq!(measure.check("5ft"), Err(Error::validation("`5ft` has an unknown unit `ft`, expected cm or in")));
+        assert_eq!(measure.check("cm"), Err(Error::validation("`cm` does not start with a number")));
+        assert!(measure.check("abc190cmxyz").is_err());
+    }
+
+    #[test]
+    fn test_parse_rule_errors() {
+        assert_eq!("colour red".parse::<Rule>().err(), Some(Error::parse(
+            "unknown rule `colour`, expected any, year, int, regex, one-of or measure"
+        )));
+        assert_eq!("int 1..10".parse::<Rule>().err(), Some(Error::parse("invalid range `1..10`, expected `<min>-<max>`")));
+        assert_eq!("int 1-10cm".parse::<Rule>().err(), Some(Error::parse("invalid range `1-10cm`, expected `<min>-<max>`")));
+        assert_eq!("measure 1-10".parse::<Rule>().err(), Some(Error::parse("range `1-10` has no unit")));
+        assert_eq!("one-of".parse::<Rule>().err(), Some(Error::parse("`one-of` needs at least one value")));
+        assert!("regex [a-".parse::<Rule>().is_err());
+    }
+
+    #[test]
+    fn test_schema() {
+        let schema: Schema = "# a comment\n\nname required regex [a-z]+\nage optional int 0-150\n".parse().unwrap();
+
+        assert_eq!(schema.fields().iter().map(|f| (f.key.as_str(), f.required)).collect::<Vec<_>>(),
+            vec![("name", true), ("age", false)]);
+        assert_eq!(schema.check(&values(&[("name", "bob")])), Ok(()));
+        assert_eq!(schema.check(&values(&[("name", "bob"), ("age", "151"), ("pet", "cat")])),
+            Err(Error::validation("151 is not within 0-150")));
+        assert_eq!(schema.check(&values(&[("age", "40")])), Err(Error::validation("missing field `name`")));
+    }
+
+    #[test]
+    fn test_report() {
+        let schema: Schema = "name required regex [a-z]+\nage required int 0-150\nhgt optional measure 1-3m\n".parse().unwrap();
+        let errors = schema.report(&values(&[("age", "old"), ("hgt", "2ft")]));
+
+        assert_eq!(errors, vec![
+            FieldError { key: "name".to_string(), value: None, problem: Problem::Missing, reason: "missing field `name`".to_string() },
+            FieldError { key: "age".to_string(), value: Some("old".to_string()), problem: Problem::Malformed, reason: "`old` is not a number".to_string() },
+            FieldError { key: "hgt".to_string(), value: Some("2ft".to_string()), problem: Problem::BadUnit, reason: "`2ft` has an unknown unit `ft`, expected m".to_string() },
+        ]);
+        assert_eq!(schema.check(&values(&[("age", "old")])), Err(Error::validation("missing field `name`")));
+        assert!(schema.report(&values(&[("name", "bob"), ("age", "3")])).is_empty());
+
+        let missing = schema.missing(&values(&[("age", "old"), ("hgt", "2ft")]));
+        assert_eq!(missing.iter().map(|e| e.key.as_str()).collect::<Vec<_>>(), vec!["name"]);
+        assert!(schema.missing(&values(&[("name", "?"), ("age", "old")])).is_empty());
+    }
+
+    #[test]
+    fn test_parse_schema_errors() {
+        assert_eq!(
+            "name required\nage maybe int 0-150\n".parse::<Schema>().err(),
+            Some(Error::parse("unknown rule ``, expected any, year, int, regex, one-of or measure").in_field("name").at_line(1))
+        );
+        assert_eq!(
+            "name required any\nage maybe int 0-150\n".parse::<Schema>().err(),
+            Some(Error::parse("expected `required` or `optional`, got `maybe`").in_field("age").at_line(2))
+        );
+        assert_eq!(
+            "name required any\nname optional any\n".parse::<Schema>().err(),
+            Some(Error::parse("field `name` is declared twice").at_line(2))
+        );
+    }
+
+    #[test]
+    fn test_passport_schema() {
+        let schema = Schema::passport();
+        let keys: Vec<&str> = schema.fields().iter().map(|f| f.key.as_str()).collect();
+
+        assert_eq!(keys, vec!["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid", "cid"]);
+        assert_eq!(schema.field("cid").map(|f| f.required), Some(false));
+        assert!(std::ptr::eq(schema, Schema::passport()));
+
+        let loaded = Schema::load("src/day4/passport.schema").unwrap();
+        assert_eq!(loaded.fields().len(), schema.fields().len());
+    }
+}
@@ -0,0 +1,325 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::str::FromStr;
+
+use crate::error::{Error, Result};
+use crate::json;
+use super::Passport;
+use super::values::{EyeColor, Height};
+
+/// Heights are counted in buckets of this many centimetres.
+const HEIGHT_BUCKET: u32 = 10;
+
+/// Birth years are counted in buckets of this many years, unless asked
+/// otherwise with `Stats::with_birth_bucket`.
+pub const BIRTH_BUCKET: u16 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Table,
+    Csv,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Format> {
+        match s {
+            "table" => Ok(Format::Table),
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            _ => Err(Error::parse(format!("unknown format `{}`, expected table, csv or json", s))),
+        }
+    }
+}
+
+/// Number of passports per bucket of one statistic, and of passports whose
+/// value for it is missing or doesn't parse, like an `hgt` without unit
+/// that passed a presence check.
+#[derive(Debug, PartialEq)]
+pub struct Counts<K: Ord> {
+    pub buckets: BTreeMap<K, usize>,
+    pub invalid: usize,
+}
+
+impl<K: Ord> Default for Counts<K> {
+    fn default() -> Counts<K> {
+        Counts { buckets: BTreeMap::new(), invalid: 0 }
+    }
+}
+
+impl<K: Ord> Counts<K> {
+    fn add(&mut self, key: Option<K>) {
+        match key {
+            Some(key) => *self.buckets.entry(key).or_insert(0) += 1,
+            None => self.invalid += 1,
+        }
+    }
+
+    /// Every bucket as a `(label, count)` pair, `invalid` last when there
+    /// are any.
+    fn labelled<F: Fn(&K) -> String>(&self, label: F) -> Vec<(String, usize)> {
+        let mut labelled: Vec<(String, usize)> = self.buckets.iter().map(|(k, n)| (label(k), *n)).collect();
+
+        if self.invalid > 0 {
+            labelled.push(("invalid".to_string(), self.invalid));
+        }
+
+        labelled
+    }
+}
+
+fn value<T: FromStr>(passport: &Passport, key: &str) -> Option<T> {
+    passport.get(key)?.parse().ok()
+}
+
+/// What a batch of passports looks like.
+#[derive(Debug, PartialEq)]
+pub struct Stats {
+    pub passports: usize,
+    /// Number of passports per year of birth.
+    pub birth_years: Counts<u16>,
+    /// The number of years of birth shown as one bucket.
+    pub birth_bucket: u16,
+    pub eye_colors: Counts<EyeColor>,
+    /// Number of passports per height bucket in cm, by the lowest height in the bucket.
+    pub heights: Counts<u32>,
+    pub missing_cid: usize,
+}
+
+impl Default for Stats {
+    fn default() -> Stats {
+        Stats {
+            passports: 0,
+            birth_years: Counts::default(),
+            birth_bucket: BIRTH_BUCKET,
+            eye_colors: Counts::default(),
+            heights: Counts::default(),
+            missing_cid: 0,
+        }
+    }
+}
+
+impl Stats {
+    /// Collects statistics over passports of the puzzle. Every passport is
+    /// counted; a value that is missing or doesn't parse, as can happen
+    /// after a presence check, is counted as `invalid` for its statistic.
+    pub fn collect(passports: &[Passport]) -> Stats {
+        let mut stats = Stats::default();
+
+        for passport in passports.iter() {
+            let (byr, ecl, hgt) = match passport.typed() {
+                Some(typed) => (Some(typed.byr), Some(typed.ecl), Some(typed.hgt)),
+                // some value doesn't parse, so each is read on its own
+                None => (value(passport, "byr"), value(passport, "ecl"), value::<Height>(passport, "hgt")),
+            };
+
+            stats.passports += 1;
+            stats.birth_years.add(byr);
+            stats.eye_colors.add(ecl);
+            stats.heights.add(hgt.map(|hgt| hgt.to_cm().round() as u32 / HEIGHT_BUCKET * HEIGHT_BUCKET));
+
+            if passport.get("cid").is_none() {
+                stats.missing_cid += 1;
+            }
+        }
+
+        stats
+    }
+
+    /// Shows the years of birth in buckets of `years` years, `1` for a
+    /// count per year. Zero is taken as one.
+    pub fn with_birth_bucket(self, years: u16) -> Stats {
+        Stats { birth_bucket: years.max(1), ..self }
+    }
+
+    /// The number of passports per bucket of `birth_bucket` years, by the
+    /// first year of the bucket.
+    pub fn birth_buckets(&self) -> Counts<u16> {
+        let mut buckets = Counts { buckets: BTreeMap::new(), invalid: self.birth_years.invalid };
+
+        for (year, n) in self.birth_years.buckets.iter() {
+            *buckets.buckets.entry(year / self.birth_bucket * self.birth_bucket).or_insert(0) += n;
+        }
+
+        buckets
+    }
+
+    /// The share of passports without a `cid`, between 0 and 1.
+    pub fn missing_cid_share(&self) -> f64 {
+        if self.passports == 0 {
+            0.0
+        } else {
+            self.missing_cid as f64 / self.passports as f64
+        }
+    }
+
+    /// Every count as a `(statistic, bucket, count)` row.
+    fn rows(&self) -> Vec<(&'static str, String, usize)> {
+        let mut rows = vec![("passports", "all".to_string(), self.passports), ("cid", "missing".to_string(), self.missing_cid)];
+        let bucket = self.birth_bucket;
+
+        let births = self.birth_buckets().labelled(|year| match bucket {
+            1 => year.to_string(),
+            _ => format!("{}-{}", year, year.saturating_add(bucket - 1)),
+        });
+        let eye_colors = self.eye_colors.labelled(|color| color.to_string());
+        let heights = self.heights.labelled(|cm| format!("{}-{}cm", cm, cm + HEIGHT_BUCKET - 1));
+
+        rows.extend(births.into_iter().map(|(label, n)| ("birth year", label, n)));
+        rows.extend(eye_colors.into_iter().map(|(label, n)| ("eye color", label, n)));
+        rows.extend(heights.into_iter().map(|(label, n)| ("height", label, n)));
+        rows
+    }
+
+    pub fn render(&self, format: Format) -> String {
+        match format {
+            Format::Table => self.to_table(),
+            Format::Csv => self.to_csv(),
+            Format::Json => self.to_json(),
+        }
+    }
+
+    fn to_table(&self) -> String {
+        let mut out = String::new();
+        let mut section = "";
+
+        writeln!(out, "{} passports, {:.1}% without cid", self.passports, self.missing_cid_share() * 100.0).unwrap();
+
+        for (statistic, bucket, n) in self.rows().into_iter().skip(2) {
+            if statistic != section {
+                writeln!(out, "\n{}", statistic).unwrap();
+                section = statistic;
+            }
+
+            writeln!(out, "  {:<10} {:>5}", bucket, n).unwrap();
+        }
+
+        out
+    }
+
+    fn to_csv(&self) -> String {
+        let mut out = "statistic,bucket,count\n".to_string();
+
+        for (statistic, bucket, n) in self.rows() {
+            writeln!(out, "{},{},{}", statistic, bucket, n).unwrap();
+        }
+
+        out
+    }
+
+    fn to_json(&self) -> String {
+        json::object(&[
+            ("passports", self.passports.to_string()),
+            ("missing_cid", self.missing_cid.to_string()),
+            ("birth_bucket", self.birth_bucket.to_string()),
+            ("birth_years", json_counts(&self.birth_buckets())),
+            ("eye_colors", json_counts(&self.eye_colors)),
+            ("heights_cm", json_counts(&self.heights)),
+        ]) + "\n"
+    }
+}
+
+fn json_counts<K: Ord + ToString>(counts: &Counts<K>) -> String {
+    let fields = counts.labelled(|k| k.to_string());
+    json::object(&fields.iter().map(|(k, n)| (k.as_str(), n.to_string())).collect::<Vec<_>>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day4::Check;
+    use crate::day4::schema::Schema;
+
+    fn passports() -> Vec<Passport> {
+        vec![
+            "pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980 hcl:#623a2f",
+            "eyr:2029 ecl:blu cid:129 byr:1989 iyr:2014 pid:896056539 hcl:#a97842 hgt:165cm",
+            "hcl:#888785 hgt:164cm byr:2001 iyr:2015 cid:88 pid:545766238 ecl:hzl eyr:2022",
+            "iyr:2010 hgt:158cm hcl:#b6652a ecl:blu byr:1944 eyr:2021 pid:093154719",
+        ]
+        .into_iter()
+        .map(|p| p.parse().unwrap())
+        .collect()
+    }
+
+    #[test]
+    fn test_collect() {
+        let stats = Stats::collect(&passports());
+
+        assert_eq!(stats.passports, 4);
+        assert_eq!(stats.birth_years.buckets.into_iter().collect::<Vec<_>>(), vec![(1944, 1), (1980, 1), (1989, 1), (2001, 1)]);
+        assert_eq!(stats.eye_colors.buckets.into_iter().collect::<Vec<_>>(), vec![(EyeColor::Blue, 2), (EyeColor::Green, 1), (EyeColor::Hazel, 1)]);
+        // 74in is 188cm
+        assert_eq!(stats.heights.buckets.into_iter().collect::<Vec<_>>(), vec![(150, 1), (160, 2), (180, 1)]);
+        assert_eq!(stats.heights.invalid, 0);
+        assert_eq!(stats.missing_cid, 2);
+        assert_eq!(Stats::default().missing_cid_share(), 0.0);
+    }
+
+    #[test]
+    fn test_collect_invalid_values() {
+        let passports: Vec<Passport> = vec![
+            "pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980 hcl:#623a2f",
+            "pid:0 hgt:190 ecl:zzz iyr:2012 eyr:2030 byr:nineteen hcl:z",
+        ]
+        .into_iter()
+        .map(|p| Passport::parse_checked(p, Schema::passport(), Check::Presence).unwrap())
+        .collect();
+
+        let stats = Stats::collect(&passports);
+
+        assert_eq!(stats.passports, 2);
+        assert_eq!(stats.birth_years, Counts { buckets: vec![(1980, 1)].into_iter().collect(), invalid: 1 });
+        assert_eq!(stats.eye_colors.invalid, 1);
+        assert_eq!(stats.heights.invalid, 1);
+        assert!(stats.render(Format::Csv).ends_with("eye color,grn,1\neye color,invalid,1\nheight,180-189cm,1\nheight,invalid,1\n"));
+    }
+
+    #[test]
+    fn test_birth_bucket() {
+        let stats = Stats::collect(&passports());
+
+        assert_eq!(stats.birth_buckets().buckets.into_iter().collect::<Vec<_>>(), vec![(1940, 1), (1980, 2), (2000, 1)]);
+
+        let stats = stats.with_birth_bucket(25);
+        assert_eq!(stats.birth_buckets().buckets.into_iter().collect::<Vec<_>>(), vec![(1925, 1), (1975, 2), (2000, 1)]);
+
+        let per_year = stats.with_birth_bucket(0).render(Format::Csv);
+        assert!(per_year.contains("birth year,1944,1\nbirth year,1980,1\nbirth year,1989,1\nbirth year,2001,1\n"));
+    }
+
+    #[test]
+    fn test_render() {
+        let stats = Stats::collect(&passports()[..2]);
+
+        assert_eq!(stats.render(Format::Csv), "statistic,bucket,count
+passports,all,2
+cid,missing,1
+birth year,1980-1989,2
+eye color,blu,1
+eye color,grn,1
+height,160-169cm,1
+height,180-189cm,1
+");
+        assert_eq!(
+            stats.render(Format::Json),
+            r#"{"passports":2,"missing_cid":1,"birth_bucket":10,"birth_years":{"1980":2},"eye_colors":{"blu":1,"grn":1},"heights_cm":{"160":1,"180":1}}"#.to_string() + "\n"
+        );
+        assert_eq!(stats.render(Format::Table), "2 passports, 50.0% without cid
+
+birth year
+  1980-1989      2
+
+eye color
+  blu            1
+  grn            1
+
+height
+  160-169cm      1
+  180-189cm      1
+");
+        assert_eq!("xml".parse::<Format>(), Err(Error::parse("unknown format `xml`, expected table, csv or json")));
+    }
+}
@@ -0,0 +1,285 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum LengthUnit {
+    Cm,
+    In,
+}
+
+impl LengthUnit {
+    fn as_str(self) -> &'static str {
+        match self {
+            LengthUnit::Cm => "cm",
+            LengthUnit::In => "in",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Height {
+    pub value: u32,
+    pub unit: LengthUnit,
+}
+
+impl Height {
+    pub fn to_cm(self) -> f64 {
+        match self.unit {
+            LengthUnit::Cm => f64::from(self.value),
+            LengthUnit::In => f64::from(self.value) * 2.54,
+        }
+    }
+}
+
+impl FromStr for Height {
+    type Err = Error;
+
+    /// Parses a number directly followed by `cm` or `in`, e.g. `183cm`.
+    fn from_str(s: &str) -> Result<Height> {
+        let digits = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (value, unit) = s.split_at(digits);
+
+        let unit = match unit {
+            "cm" => LengthUnit::Cm,
+            "in" => LengthUnit::In,
+            _ => return Err(Error::parse(format!("invalid height `{}`, expected a number of cm or in", s))),
+        };
+        let value = value
+            .parse()
+            .map_err(|_| Error::parse(format!("invalid height `{}`, expected a number of cm or in", s)))?;
+
+        Ok(Height { value, unit })
+    }
+}
+
+impl fmt::Display for Height {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.value, self.unit.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RgbColor {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl FromStr for RgbColor {
+    type Err = Error;
+
+    /// Parses `#rrggbb`, in lower or upper case.
+    fn from_str(s: &str) -> Result<RgbColor> {
+        let invalid = || Error::parse(format!("invalid color `{}`, expected `#rrggbb`", s));
+
+        let hex = s.strip_prefix('#').filter(|h| h.len() == 6 && h.is_ascii()).ok_or_else(invalid)?;
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+
+        Ok(RgbColor { r: channel(0)?, g: channel(2)?, b: channel(4)? })
+    }
+}
+
+impl fmt::Display for RgbColor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum EyeColor {
+    Amber,
+    Blue,
+    Brown,
+    Gray,
+    Green,
+    Hazel,
+    Other,
+}
+
+impl EyeColor {
+    pub const ALL: [EyeColor; 7] = [
+        EyeColor::Amber,
+        EyeColor::Blue,
+        EyeColor::Brown,
+        EyeColor::Gray,
+        EyeColor::Green,
+        EyeColor::Hazel,
+        EyeColor::Other,
+    ];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            EyeColor::Amber => "amb",
+            EyeColor::Blue => "blu",
+            EyeColor::Brown => "brn",
+            EyeColor::Gray => "gry",
+            EyeColor::Green => "grn",
+            EyeColor::Hazel => "hzl",
+            EyeColor::Other => "oth",
+        }
+    }
+}
+
+impl FromStr for EyeColor {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<EyeColor> {
+        EyeColor::ALL
+            .iter()
+            .copied()
+            .find(|c| c.code() == s)
+            .ok_or_else(|| Error::parse(format!("invalid eye color `{}`", s)))
+    }
+}
+
+impl fmt::Display for EyeColor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+/// A nine digit passport id, kept as text so leading zeros survive.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PassportId(String);
+
+impl PassportId {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for PassportId {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<PassportId> {
+        if s.len() == 9 && s.bytes().all(|b| b.is_ascii_digit()) {
+            Ok(PassportId(s.to_string()))
+        } else {
+            Err(Error::parse(format!("invalid passport id `{}`, expected nine digits", s)))
+        }
+    }
+}
+
+impl fmt::Display for PassportId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The fields of a passport of the puzzle in their types, parsed once when
+/// the passport is made, see `Passport::typed`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypedPassport {
+    pub byr: u16,
+    pub iyr: u16,
+    pub eyr: u16,
+    pub hgt: Height,
+    pub hcl: RgbColor,
+    pub ecl: EyeColor,
+    pub pid: PassportId,
+    pub cid: Option<String>,
+}
+
+fn raw<'a>(fields: &'a [(String, String)], key: &str) -> Result<&'a str> {
+    fields
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.as_str())
+        .ok_or_else(|| Error::parse(format!("missing field `{}`", key)))
+}
+
+fn field<T: FromStr<Err = Error>>(fields: &[(String, String)], key: &str) -> Result<T> {
+    raw(fields, key)?.parse().map_err(|e: Error| e.in_field(key))
+}
+
+fn year(fields: &[(String, String)], key: &str) -> Result<u16> {
+    let value = raw(fields, key)?;
+    value.parse().map_err(|_| Error::parse(format!("`{}` is not a year", value)).in_field(key))
+}
+
+impl TypedPassport {
+    /// Fails on the first field of the puzzle that is missing or doesn't
+    /// parse.
+    pub(super) fn from_fields(fields: &[(String, String)]) -> Result<TypedPassport> {
+        Ok(TypedPassport {
+            byr: year(fields, "byr")?,
+            iyr: year(fields, "iyr")?,
+            eyr: year(fields, "eyr")?,
+            hgt: field(fields, "hgt")?,
+            hcl: field(fields, "hcl")?,
+            ecl: field(fields, "ecl")?,
+            pid: field(fields, "pid")?,
+            cid: raw(fields, "cid").ok().map(|c| c.to_string()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day4::Passport;
+
+    #[test]
+    fn test_height() {
+        assert_eq!("183cm".parse(), Ok(Height { value: 183, unit: LengthUnit::Cm }));
+        assert_eq!("60in".parse::<Height>().map(|h| h.to_cm()), Ok(152.4));
+        assert_eq!("60in".parse::<Height>().map(|h| h.to_string()), Ok("60in".to_string()));
+        assert_eq!("183".parse::<Height>(), Err(Error::parse("invalid height `183`, expected a number of cm or in")));
+        assert!("cm".parse::<Height>().is_err());
+        assert!("1.5in".parse::<Height>().is_err());
+    }
+
+    #[test]
+    fn test_rgb_color() {
+        assert_eq!("#7d3b0c".parse(), Ok(RgbColor { r: 0x7d, g: 0x3b, b: 0x0c }));
+        assert_eq!("#7D3B0C".parse::<RgbColor>().map(|c| c.to_string()), Ok("#7d3b0c".to_string()));
+        assert_eq!("7d3b0c".parse::<RgbColor>(), Err(Error::parse("invalid color `7d3b0c`, expected `#rrggbb`")));
+        assert!("#7d3b0".parse::<RgbColor>().is_err());
+        assert!("#7d3b0z".parse::<RgbColor>().is_err());
+        assert!("#ééé".parse::<RgbColor>().is_err());
+    }
+
+    #[test]
+    fn test_eye_color() {
+        assert_eq!("hzl".parse(), Ok(EyeColor::Hazel));
+        assert!(EyeColor::ALL.iter().all(|c| c.code().parse() == Ok(*c)));
+        assert_eq!("wat".parse::<EyeColor>(), Err(Error::parse("invalid eye color `wat`")));
+    }
+
+    #[test]
+    fn test_passport_id() {
+        let pid: PassportId = "000000001".parse().unwrap();
+        assert_eq!(pid.as_str(), "000000001");
+        assert!("0123456789".parse::<PassportId>().is_err());
+        assert!("12345678a".parse::<PassportId>().is_err());
+    }
+
+    #[test]
+    fn test_typed_passport() {
+        let passport: Passport = "pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980 hcl:#623a2f".parse().unwrap();
+
+        assert_eq!(passport.typed(), Some(&TypedPassport {
+            byr: 1980,
+            iyr: 2012,
+            eyr: 2030,
+            hgt: Height { value: 74, unit: LengthUnit::In },
+            hcl: RgbColor { r: 0x62, g: 0x3a, b: 0x2f },
+            ecl: EyeColor::Green,
+            pid: "087499704".parse().unwrap(),
+            cid: None,
+        }));
+
+        let schema = "hgt required any\nbyr optional any\n".parse().unwrap();
+        let loose = Passport::parse_with("hgt:tall byr:1980", &schema).unwrap();
+        assert_eq!(loose.typed(), None);
+
+        let fields = |s: &str| s.split(' ').map(|f| (f[..3].to_string(), f[4..].to_string())).collect::<Vec<_>>();
+        assert_eq!(TypedPassport::from_fields(&fields("hgt:tall")), Err(Error::parse("missing field `byr`")));
+        assert_eq!(
+            TypedPassport::from_fields(&fields("byr:1980 iyr:2012 eyr:2030 hgt:tall")),
+            Err(Error::parse("invalid height `tall`, expected a number of cm or in").in_field("hgt"))
+        );
+    }
+}
@@ -1,47 +1,202 @@
 #![allow(dead_code)]
 
-fn find_seat(lines: Vec<String>) -> Option<usize> {
-    let mut seats: Vec<(usize, usize)> = vec![];
+use std::fmt;
+use std::str::FromStr;
 
-    for line in lines.iter() {
-        let row_binary_str = &line[..7].replace("F", "0").replace("B", "1");
-        let row = usize::from_str_radix(&row_binary_str, 2).unwrap();
+use crate::error::{Error, Result};
+use crate::solution::Solution;
 
-        let col_binary_str = &line[7..10].replace("L", "0").replace("R", "1");
-        let col = usize::from_str_radix(&col_binary_str, 2).unwrap();
+pub struct Day5;
 
-        seats.push((row, col));
+const ROWS: usize = 128;
+const COLUMNS: usize = 8;
+
+/// A seat, as encoded on a boarding pass: 7 `F`/`B` halvings for the row
+/// followed by 3 `L`/`R` halvings for the column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BoardingPass {
+    row: usize,
+    column: usize,
+}
+
+impl BoardingPass {
+    pub fn new(row: usize, column: usize) -> Result<BoardingPass> {
+        if row >= ROWS || column >= COLUMNS {
+            return Err(Error::validation(format!("there is no seat at row {}, column {}", row, column)));
+        }
+
+        Ok(BoardingPass { row, column })
+    }
+
+    pub fn from_seat_id(seat_id: usize) -> Result<BoardingPass> {
+        BoardingPass::new(seat_id / COLUMNS, seat_id % COLUMNS)
     }
 
-    let mut seat_ids: Vec<usize> = seats.iter().map(|(r, c)| r * 8 + c).collect();
-    seat_ids.sort();
+    pub fn row(&self) -> usize {
+        self.row
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    pub fn seat_id(&self) -> usize {
+        self.row * COLUMNS + self.column
+    }
+}
+
+impl FromStr for BoardingPass {
+    type Err = Error;
 
-    let mut prev_seat = 59;
-    let mut missing_seat = None;
+    fn from_str(s: &str) -> Result<BoardingPass> {
+        if s.chars().count() != 10 {
+            return Err(Error::parse(format!("boarding pass `{}` should be 10 characters long", s)));
+        }
+
+        let mut row = 0;
+        let mut column = 0;
+
+        for (i, c) in s.chars().enumerate() {
+            let (half, bit) = match (i < 7, c) {
+                (true, 'F') => (&mut row, 0),
+                (true, 'B') => (&mut row, 1),
+                (false, 'L') => (&mut column, 0),
+                (false, 'R') => (&mut column, 1),
+                (true, _) => return Err(invalid_char(s, c, "F or B").at_column(i + 1)),
+                (false, _) => return Err(invalid_char(s, c, "L or R").at_column(i + 1)),
+            };
+
+            *half = *half << 1 | bit;
+        }
+
+        Ok(BoardingPass { row, column })
+    }
+}
+
+fn invalid_char(pass: &str, c: char, expected: &str) -> Error {
+    Error::parse(format!("invalid character `{}` in boarding pass `{}`, expected {}", c, pass, expected))
+}
 
-    for &seat in seat_ids.iter() {
-        if seat < 60 { continue; }
+impl fmt::Display for BoardingPass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for bit in (0..7).rev() {
+            write!(f, "{}", if self.row >> bit & 1 == 1 { 'B' } else { 'F' })?;
+        }
 
-        if seat - 1 != prev_seat {
-            missing_seat = Some(seat - 1);
-            break;
+        for bit in (0..3).rev() {
+            write!(f, "{}", if self.column >> bit & 1 == 1 { 'R' } else { 'L' })?;
         }
 
-        prev_seat = seat;
+        Ok(())
+    }
+}
+
+fn seat_id(line: &str) -> Result<usize> {
+    line.parse::<BoardingPass>().map(|pass| pass.seat_id())
+}
+
+fn find_seat(seat_ids: &[usize]) -> Option<usize> {
+    let mut seat_ids = seat_ids.to_vec();
+    seat_ids.sort_unstable();
+
+    seat_ids
+        .windows(2)
+        .find(|pair| pair[1] - pair[0] == 2)
+        .map(|pair| pair[0] + 1)
+}
+
+impl Solution for Day5 {
+    const DAY: u8 = 5;
+
+    type Input = Vec<usize>;
+    type Answer = usize;
+
+    fn parse(input: &str) -> Result<Vec<usize>> {
+        input
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| seat_id(line.trim()).map_err(|e| e.at_line(i + 1)))
+            .collect()
     }
 
-    missing_seat
+    fn part_one(seat_ids: &Vec<usize>) -> Result<usize> {
+        seat_ids.iter().max().copied().ok_or_else(|| Error::validation("no boarding passes"))
+    }
+
+    fn part_two(seat_ids: &Vec<usize>) -> Result<usize> {
+        find_seat(seat_ids).ok_or_else(|| Error::validation("no free seat found"))
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::input::{read_lines};
+    use crate::input::{read_file};
     use super::*;
 
+    #[test]
+    fn test_seat_id() {
+        assert_eq!(seat_id("FBFBBFFRLR"), Ok(357));
+        assert_eq!(seat_id("BFFFBBFRRR"), Ok(567));
+        assert_eq!(seat_id("FFFBBBFRRR"), Ok(119));
+        assert_eq!(seat_id("BBFFBBFRLL"), Ok(820));
+
+        assert!(seat_id("FBFBBFFRL").is_err());
+        assert!(seat_id("FBFBXFFRLR").is_err());
+    }
+
+    #[test]
+    fn test_boarding_pass() {
+        let pass: BoardingPass = "FBFBBFFRLR".parse().unwrap();
+        assert_eq!((pass.row(), pass.column(), pass.seat_id()), (44, 5, 357));
+        assert_eq!(pass.to_string(), "FBFBBFFRLR");
+
+        assert_eq!(
+            "FBFBBFFRL".parse::<BoardingPass>(),
+            Err(Error::parse("boarding pass `FBFBBFFRL` should be 10 characters long"))
+        );
+        assert_eq!(
+            "FBFBXFFRLR".parse::<BoardingPass>(),
+            Err(Error::parse("invalid character `X` in boarding pass `FBFBXFFRLR`, expected F or B").at_column(5))
+        );
+        assert_eq!(
+            "FBFBBFFRLB".parse::<BoardingPass>(),
+            Err(Error::parse("invalid character `B` in boarding pass `FBFBBFFRLB`, expected L or R").at_column(10))
+        );
+        assert_eq!(
+            "FBFBBFFLRé".parse::<BoardingPass>(),
+            Err(Error::parse("invalid character `é` in boarding pass `FBFBBFFLRé`, expected L or R").at_column(10))
+        );
+        assert!("FBFBBFéRL".parse::<BoardingPass>().is_err());
+        assert!("fbfbbffrlr".parse::<BoardingPass>().is_err());
+    }
+
+    #[test]
+    fn test_boarding_pass_round_trip() {
+        for seat_id in 0..ROWS * COLUMNS {
+            let pass = BoardingPass::from_seat_id(seat_id).unwrap();
+
+            assert_eq!(pass.seat_id(), seat_id);
+            assert_eq!(pass.to_string().parse(), Ok(pass));
+        }
+
+        assert_eq!(BoardingPass::from_seat_id(0).map(|p| p.to_string()), Ok("FFFFFFFLLL".to_string()));
+        assert_eq!(BoardingPass::from_seat_id(1023).map(|p| p.to_string()), Ok("BBBBBBBRRR".to_string()));
+        assert_eq!(BoardingPass::from_seat_id(1024), Err(Error::validation("there is no seat at row 128, column 0")));
+        assert!(BoardingPass::new(3, 8).is_err());
+    }
+
+    #[test]
+    fn test_part_one() {
+        let seat_ids = Day5::parse(&read_file("input/day5.txt").unwrap()).unwrap();
+
+        assert_eq!(Day5::part_one(&seat_ids), Ok(874));
+    }
+
     #[test]
     fn test_find_seat() {
-        let lines = read_lines("input/day5.txt");
+        let seat_ids = Day5::parse(&read_file("input/day5.txt").unwrap()).unwrap();
 
-        assert_eq!(find_seat(lines), Some(594));
+        assert_eq!(find_seat(&seat_ids), Some(594));
     }
 }
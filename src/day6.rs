@@ -2,6 +2,11 @@
 
 use std::collections::HashSet;
 use std::collections::HashMap;
+use crate::error::Result;
+use crate::input::read_records;
+use crate::solution::Solution;
+
+pub struct Day6;
 
 fn group_to_answers(group: &str) -> usize {
     let mut map: HashSet<char> = HashSet::new();
@@ -17,7 +22,7 @@ fn group_to_answers(group: &str) -> usize {
 
 fn group_to_answers_for_all(group: &str) -> usize {
     let mut answers: HashMap<char, usize> = HashMap::new();
-    let people: Vec<&str> = group.split("\n").filter(|&n| n != "").collect();
+    let people: Vec<&str> = group.split('\n').filter(|&n| !n.is_empty()).collect();
 
     for person in people.iter() {
         for c in person.chars() {
@@ -35,36 +40,41 @@ fn group_to_answers_for_all(group: &str) -> usize {
     })
 }
 
-fn part_one(groups: Vec<&str>) -> usize {
-    groups.iter().fold(0, |acc, n| acc + group_to_answers(n))
-}
+impl Solution for Day6 {
+    const DAY: u8 = 6;
+
+    type Input = Vec<String>;
+    type Answer = usize;
+
+    fn parse(input: &str) -> Result<Vec<String>> {
+        read_records(input.as_bytes())
+    }
+
+    fn part_one(groups: &Vec<String>) -> Result<usize> {
+        Ok(groups.iter().fold(0, |acc, n| acc + group_to_answers(n)))
+    }
 
-fn part_two(groups: Vec<&str>) -> usize {
-    groups.iter().fold(0, |acc, n| acc + group_to_answers_for_all(n))
+    fn part_two(groups: &Vec<String>) -> Result<usize> {
+        Ok(groups.iter().fold(0, |acc, n| acc + group_to_answers_for_all(n)))
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::input::{read_file};
+    use crate::input::{open};
     use super::*;
 
     #[test]
     fn test_part_one() {
-        let input = read_file("input/day6.txt");
-        let groups: Vec<&str> = input
-            .split("\n\n")
-            .collect();
+        let groups = read_records(open("input/day6.txt").unwrap()).unwrap();
 
-        assert_eq!(part_one(groups), 6775);
+        assert_eq!(Day6::part_one(&groups), Ok(6775));
     }
 
     #[test]
     fn test_part_two() {
-        let input = read_file("input/day6.txt");
-        let groups: Vec<&str> = input
-            .split("\n\n")
-            .collect();
+        let groups = read_records(open("input/day6.txt").unwrap()).unwrap();
 
-        assert_eq!(part_two(groups), 3356);
+        assert_eq!(Day6::part_two(&groups), Ok(3356));
     }
 }
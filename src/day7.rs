@@ -1,8 +1,14 @@
 #![allow(dead_code)]
 
 use std::str::FromStr;
+use std::sync::OnceLock;
 use regex::Regex;
 use std::fmt;
+use crate::error::{Error, Result};
+use crate::input::parse_lines;
+use crate::solution::Solution;
+
+pub struct Day7;
 
 #[derive(Debug, PartialEq)]
 struct Rule {
@@ -11,47 +17,53 @@ struct Rule {
 }
 
 impl FromStr for Rule {
-    type Err = &'static str;
+    type Err = Error;
 
-    fn from_str(input: &str) -> Result<Rule, Self::Err> {
-        let re = Regex::new(r"(\d+) ([a-z|\s]+) (bag|bags)$").unwrap();
-        let matches = re.captures(&input).unwrap();
+    fn from_str(input: &str) -> Result<Rule> {
+        static RULE: OnceLock<Regex> = OnceLock::new();
+        let re = RULE.get_or_init(|| Regex::new(r"(\d+) ([a-z|\s]+) (bag|bags)$").unwrap());
+        let matches = re
+            .captures(input)
+            .ok_or_else(|| Error::parse(format!("bagrule -> can't parse `{}`", input.trim())))?;
 
         let color = matches.get(2)
-            .ok_or("bagrule -> can't find color")?
+            .ok_or_else(|| Error::parse("bagrule -> can't find color"))?
             .as_str()
             .into();
 
         let amount = matches.get(1)
-            .ok_or("bagrule -> can't find amount")?
+            .ok_or_else(|| Error::parse("bagrule -> can't find amount"))?
             .as_str()
             .parse()
-            .map_err(|_| "bagrule -> can't parse amount")?;
+            .map_err(|_| Error::parse("bagrule -> can't parse amount"))?;
 
         Ok(Rule { color, amount })
     }
 }
 
 #[derive(Debug, PartialEq)]
-struct Bag {
+pub struct Bag {
     color: String,
     rules: Vec<Rule>,
 }
 
 impl FromStr for Bag {
-    type Err = &'static str;
+    type Err = Error;
 
-    fn from_str(input: &str) -> Result<Bag, Self::Err> {
-        let re = Regex::new(r"^([a-z\s]+) bags contain ([a-z\d\s,]+)\.$").unwrap();
-        let matches = re.captures(&input).unwrap();
+    fn from_str(input: &str) -> Result<Bag> {
+        static BAG: OnceLock<Regex> = OnceLock::new();
+        let re = BAG.get_or_init(|| Regex::new(r"^([a-z\s]+) bags contain ([a-z\d\s,]+)\.$").unwrap());
+        let matches = re
+            .captures(input)
+            .ok_or_else(|| Error::parse(format!("bag -> can't parse `{}`", input)))?;
 
         let color: String = matches.get(1)
-            .ok_or("bag -> can't find color")?
+            .ok_or_else(|| Error::parse("bag -> can't find color"))?
             .as_str()
             .into();
 
         let rules_string = matches.get(2)
-            .ok_or("bag -> can't find rules")?
+            .ok_or_else(|| Error::parse("bag -> can't find rules"))?
             .as_str();
 
         let rules: Vec<Rule>  = match rules_string {
@@ -75,7 +87,7 @@ impl fmt::Display for Bag {
     }
 }
 
-fn find_bags_for(bags: &Vec<Bag>, color: &str) -> usize {
+fn find_bags_for(bags: &[Bag], color: &str) -> usize {
     let mut result: Vec<&Bag> = bags
         .iter()
         .filter(|b| b.rules.iter().find(|r| r.color == color).is_some())
@@ -96,9 +108,10 @@ fn find_bags_for(bags: &Vec<Bag>, color: &str) -> usize {
         }
 
         new_bags.sort_by(|a, b| a.color.partial_cmp(&b.color).unwrap());
-        new_bags.dedup_by(|a, b| &a.color == &b.color);
+        new_bags.dedup_by(|a, b| a.color == b.color);
+        new_bags.retain(|b| !result.iter().any(|r| r.color == b.color));
 
-        if new_bags.len() == 0 {
+        if new_bags.is_empty() {
             break;
         }
 
@@ -108,24 +121,63 @@ fn find_bags_for(bags: &Vec<Bag>, color: &str) -> usize {
     }
 
     result.sort_by(|a, b| a.color.partial_cmp(&b.color).unwrap());
-    result.dedup_by(|a, b| &a.color == &b.color);
+    result.dedup_by(|a, b| a.color == b.color);
     result.len()
 }
 
-fn find_total_bags_inside(bags: &Vec<Bag>, colors: Vec<(&str, usize)>) -> usize {
-    colors.iter().fold(0, |acc, (color, times)| {
-        let bag = bags.iter().find(|&b| &b.color == color).unwrap();
+fn find_total_bags_inside(bags: &[Bag], colors: Vec<(&str, usize)>) -> Result<usize> {
+    count_bags_inside(bags, colors, &mut vec![])
+}
+
+/// `path` holds the colours of the bags we are currently inside, so a rule
+/// that leads back to one of them is reported instead of recursing forever.
+fn count_bags_inside<'a>(bags: &'a [Bag], colors: Vec<(&str, usize)>, path: &mut Vec<&'a str>) -> Result<usize> {
+    colors.iter().try_fold(0, |acc, (color, times)| {
+        let bag = bags
+            .iter()
+            .find(|&b| &b.color == color)
+            .ok_or_else(|| Error::validation(format!("no rule for {} bags", color)))?;
+
+        if path.contains(&bag.color.as_str()) {
+            return Err(Error::validation(format!(
+                "bag rules contain a cycle: {} -> {}",
+                path.join(" -> "),
+                bag.color
+            )));
+        }
 
         let bag_colors: Vec<(&str, usize)> = bag.rules.iter().map(|r| (r.color.as_str(), r.amount)).collect();
 
-        acc + times + (times * find_total_bags_inside(bags, bag_colors))
+        path.push(&bag.color);
+        let inside = count_bags_inside(bags, bag_colors, path)?;
+        path.pop();
+
+        Ok(acc + times + (times * inside))
     })
 }
 
+impl Solution for Day7 {
+    const DAY: u8 = 7;
+
+    type Input = Vec<Bag>;
+    type Answer = usize;
+
+    fn parse(input: &str) -> Result<Vec<Bag>> {
+        parse_lines(input)
+    }
+
+    fn part_one(bags: &Vec<Bag>) -> Result<usize> {
+        Ok(find_bags_for(bags, "shiny gold"))
+    }
+
+    fn part_two(bags: &Vec<Bag>) -> Result<usize> {
+        Ok(find_total_bags_inside(bags, vec![("shiny gold", 1)])? - 1)
+    }
+}
 
 #[cfg(test)]
 mod tests {
-    use crate::input::{read_lines};
+    use crate::input::{read_file};
     use super::*;
 
     #[test]
@@ -153,6 +205,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            "2 dark lavender".parse::<Rule>(),
+            Err(Error::parse("bagrule -> can't parse `2 dark lavender`"))
+        );
+
+        assert_eq!(
+            Day7::parse("faded blue bags contain no other bags.\ndotted black bags contain nothing\n"),
+            Err(Error::parse("bag -> can't parse `dotted black bags contain nothing`").at_line(2))
+        );
+    }
+
     #[test]
     fn test_find_bags_for() {
         let input: Vec<Bag> = vec![
@@ -184,18 +249,34 @@ mod tests {
             "dotted black bags contain no other bags.".parse().unwrap(),
         ];
 
-        assert_eq!(find_total_bags_inside(&input, vec![("shiny gold", 1)]) - 1, 32);
+        assert_eq!(find_total_bags_inside(&input, vec![("shiny gold", 1)]), Ok(33));
+    }
+
+    #[test]
+    fn test_find_total_bags_inside_cycle() {
+        let input: Vec<Bag> = vec![
+            "shiny gold bags contain 1 dark olive bag.".parse().unwrap(),
+            "dark olive bags contain 2 vibrant plum bags.".parse().unwrap(),
+            "vibrant plum bags contain 1 dark olive bag, 3 faded blue bags.".parse().unwrap(),
+            "faded blue bags contain no other bags.".parse().unwrap(),
+        ];
+
+        assert_eq!(find_bags_for(&input, "faded blue"), 3);
+        assert_eq!(
+            find_total_bags_inside(&input, vec![("shiny gold", 1)]),
+            Err(Error::validation("bag rules contain a cycle: shiny gold -> dark olive -> vibrant plum -> dark olive"))
+        );
     }
 
     #[test]
     fn test_part_one() {
-        let bags: Vec<Bag> = read_lines("input/day7.txt").iter().map(|l| l.parse().unwrap()).collect();
-        assert_eq!(find_bags_for(&bags, "shiny gold"), 161);
+        let bags = Day7::parse(&read_file("input/day7.txt").unwrap()).unwrap();
+        assert_eq!(Day7::part_one(&bags), Ok(161));
     }
 
     #[test]
     fn test_part_two() {
-        let bags: Vec<Bag> = read_lines("input/day7.txt").iter().map(|l| l.parse().unwrap()).collect();
-        assert_eq!(find_total_bags_inside(&bags, vec![("shiny gold", 1)]) - 1, 0);
+        let bags = Day7::parse(&read_file("input/day7.txt").unwrap()).unwrap();
+        assert_eq!(Day7::part_two(&bags), Ok(30899));
     }
 }
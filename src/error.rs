@@ -0,0 +1,137 @@
+use std::fmt;
+use std::io;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Parse {
+        line: Option<usize>,
+        column: Option<usize>,
+        field: Option<String>,
+        reason: String,
+    },
+    Validation(String),
+}
+
+impl Error {
+    pub fn parse<S: Into<String>>(reason: S) -> Error {
+        Error::Parse { line: None, column: None, field: None, reason: reason.into() }
+    }
+
+    pub fn validation<S: Into<String>>(reason: S) -> Error {
+        Error::Validation(reason.into())
+    }
+
+    /// Attaches a (1-based) line number to a parse error. Errors that already
+    /// know their line, and non-parse errors, are returned untouched.
+    pub fn at_line(self, line: usize) -> Error {
+        match self {
+            Error::Parse { line: None, column, field, reason } => Error::Parse { line: Some(line), column, field, reason },
+            e => e,
+        }
+    }
+
+    /// Attaches a (1-based) column number to a parse error, see `at_line`.
+    pub fn at_column(self, column: usize) -> Error {
+        match self {
+            Error::Parse { line, column: None, field, reason } => Error::Parse { line, column: Some(column), field, reason },
+            e => e,
+        }
+    }
+
+    /// Names the field of a record a parse error occurred in, see `at_line`.
+    pub fn in_field(self, field: &str) -> Error {
+        match self {
+            Error::Parse { line, column, field: None, reason } => {
+                Error::Parse { line, column, field: Some(field.to_string()), reason }
+            }
+            e => e,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "io error: {}", e),
+            Error::Parse { line, column, field, reason } => {
+                write!(f, "parse error")?;
+
+                match (line, column) {
+                    (Some(line), Some(column)) => write!(f, " at line {}, column {}", line, column)?,
+                    (Some(line), None) => write!(f, " at line {}", line)?,
+                    (None, Some(column)) => write!(f, " at column {}", column)?,
+                    (None, None) => (),
+                }
+
+                if let Some(field) = field {
+                    write!(f, " in `{}`", field)?;
+                }
+
+                write!(f, ": {}", reason)
+            }
+            Error::Validation(reason) => write!(f, "invalid input: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+// `io::Error` isn't comparable, so two I/O errors are considered equal when
+// they are of the same kind.
+impl PartialEq for Error {
+    fn eq(&self, other: &Error) -> bool {
+        match (self, other) {
+            (Error::Io(a), Error::Io(b)) => a.kind() == b.kind(),
+            (
+                Error::Parse { line: l1, column: c1, field: f1, reason: r1 },
+                Error::Parse { line: l2, column: c2, field: f2, reason: r2 },
+            ) => l1 == l2 && c1 == c2 && f1 == f2 && r1 == r2,
+            (Error::Validation(a), Error::Validation(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_position() {
+        let e = Error::parse("bad char").at_column(4).at_line(2);
+        assert_eq!(e, Error::Parse { line: Some(2), column: Some(4), field: None, reason: "bad char".to_string() });
+
+        // the innermost position wins
+        let e = Error::parse("bad char").at_line(2).at_line(5);
+        assert_eq!(e, Error::Parse { line: Some(2), column: None, field: None, reason: "bad char".to_string() });
+
+        let e = Error::parse("not a number").in_field("min").at_line(7);
+        assert_eq!(e, Error::Parse { line: Some(7), column: None, field: Some("min".to_string()), reason: "not a number".to_string() });
+
+        assert_eq!(Error::validation("nope").at_line(3), Error::validation("nope"));
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Error::parse("bad char").at_line(2).at_column(4).to_string(), "parse error at line 2, column 4: bad char");
+        assert_eq!(Error::parse("empty").to_string(), "parse error: empty");
+        assert_eq!(Error::parse("empty").in_field("pid").at_line(3).to_string(), "parse error at line 3 in `pid`: empty");
+        assert_eq!(Error::validation("no seat").to_string(), "invalid input: no seat");
+    }
+}
@@ -0,0 +1,309 @@
+#![allow(dead_code)]
+
+use std::str::FromStr;
+
+use crate::error::{Error, Result};
+
+/// A type that can be read from a single character of a char grid.
+pub trait Cell: Sized {
+    fn from_char(c: char) -> Result<Self>;
+}
+
+impl Cell for char {
+    fn from_char(c: char) -> Result<char> {
+        Ok(c)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Neighbourhood {
+    /// Up, down, left and right.
+    Four,
+    /// Including the diagonals.
+    Eight,
+}
+
+const FOUR: [(isize, isize); 4] = [(0, -1), (-1, 0), (1, 0), (0, 1)];
+const EIGHT: [(isize, isize); 8] = [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)];
+
+impl Neighbourhood {
+    fn offsets(self) -> &'static [(isize, isize)] {
+        match self {
+            Neighbourhood::Four => &FOUR,
+            Neighbourhood::Eight => &EIGHT,
+        }
+    }
+}
+
+/// A rectangular grid of cells stored row by row, `(0, 0)` is the top left.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    pub fn new(width: usize, height: usize, cells: Vec<T>) -> Result<Grid<T>> {
+        if cells.len() != width * height {
+            return Err(Error::validation(format!(
+                "a {}x{} grid needs {} cells, got {}", width, height, width * height, cells.len()
+            )));
+        }
+
+        Ok(Grid { width, height, cells })
+    }
+
+    /// Builds a grid from rows that all have the same length. Errors name the
+    /// (1-based) row that doesn't fit.
+    pub fn from_rows(rows: Vec<Vec<T>>) -> Result<Grid<T>> {
+        let width = rows.first().map(|r| r.len()).unwrap_or(0);
+        let height = rows.len();
+        let mut cells = Vec::with_capacity(width * height);
+
+        for (y, row) in rows.into_iter().enumerate() {
+            if row.len() != width {
+                return Err(Error::parse(format!("row has {} cells, expected {}", row.len(), width)).at_line(y + 1));
+            }
+
+            cells.extend(row);
+        }
+
+        Ok(Grid { width, height, cells })
+    }
+
+    /// Like `from_rows`, but rows shorter than the longest one are padded
+    /// with `fill` instead of being rejected.
+    pub fn from_rows_padded(mut rows: Vec<Vec<T>>, fill: T) -> Grid<T>
+    where
+        T: Clone,
+    {
+        let width = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+
+        for row in rows.iter_mut() {
+            row.resize(width, fill.clone());
+        }
+
+        Grid { width, height: rows.len(), cells: rows.into_iter().flatten().collect() }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<&T> {
+        if x < self.width && y < self.height {
+            self.cells.get(y * self.width + x)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut T> {
+        if x < self.width && y < self.height {
+            self.cells.get_mut(y * self.width + x)
+        } else {
+            None
+        }
+    }
+
+    /// Like `get`, but coordinates outside the grid wrap around on both axes.
+    pub fn get_wrapping(&self, x: isize, y: isize) -> Option<&T> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let x = x.rem_euclid(self.width as isize) as usize;
+        let y = y.rem_euclid(self.height as isize) as usize;
+        self.get(x, y)
+    }
+
+    /// The cells around `(x, y)` that are inside the grid, with their coordinates.
+    pub fn neighbours(&self, x: usize, y: usize, neighbourhood: Neighbourhood) -> impl Iterator<Item = (usize, usize, &T)> + '_ {
+        neighbourhood.offsets().iter().filter_map(move |&(dx, dy)| {
+            let nx = (x as isize).checked_add(dx)?;
+            let ny = (y as isize).checked_add(dy)?;
+
+            if nx < 0 || ny < 0 {
+                return None;
+            }
+
+            self.get(nx as usize, ny as usize).map(|cell| (nx as usize, ny as usize, cell))
+        })
+    }
+
+    pub fn row(&self, y: usize) -> Option<&[T]> {
+        if y < self.height {
+            Some(&self.cells[y * self.width..(y + 1) * self.width])
+        } else {
+            None
+        }
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        (0..self.height).map(move |y| &self.cells[y * self.width..(y + 1) * self.width])
+    }
+
+    pub fn column(&self, x: usize) -> impl Iterator<Item = &T> {
+        let cells = if x < self.width { &self.cells[x..] } else { &[] };
+        cells.iter().step_by(self.width.max(1))
+    }
+
+    pub fn columns(&self) -> impl Iterator<Item = impl Iterator<Item = &T>> {
+        (0..self.width).map(move |x| self.column(x))
+    }
+
+    /// All cells with their coordinates, row by row.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize, &T)> {
+        let width = self.width.max(1);
+        self.cells.iter().enumerate().map(move |(i, cell)| (i % width, i / width, cell))
+    }
+}
+
+/// Parses one row of cells per line, without checking that the rows have the
+/// same length. Errors point at the line and column of the offending
+/// character, blank lines between rows are rejected.
+pub fn parse_rows<T: Cell>(input: &str) -> Result<Vec<Vec<T>>> {
+    let mut rows = vec![];
+
+    for (y, line) in input.trim_end().lines().enumerate() {
+        let line = line.trim_end();
+
+        if line.is_empty() {
+            return Err(Error::parse("row is empty").at_line(y + 1));
+        }
+
+        let row = line
+            .chars()
+            .enumerate()
+            .map(|(x, c)| T::from_char(c).map_err(|e| e.at_column(x + 1)))
+            .collect::<Result<Vec<T>>>()
+            .map_err(|e| e.at_line(y + 1))?;
+
+        rows.push(row);
+    }
+
+    Ok(rows)
+}
+
+impl<T: Cell> FromStr for Grid<T> {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Grid<T>> {
+        Grid::from_rows(parse_rows(input)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Digit(u32);
+
+    impl Cell for Digit {
+        fn from_char(c: char) -> Result<Digit> {
+            c.to_digit(10).map(Digit).ok_or_else(|| Error::parse(format!("`{}` is not a digit", c)))
+        }
+    }
+
+    fn grid() -> Grid<char> {
+        "abc\ndef\n".parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let grid = grid();
+
+        assert_eq!((grid.width(), grid.height()), (3, 2));
+        assert_eq!(grid.get(0, 0), Some(&'a'));
+        assert_eq!(grid.get(2, 1), Some(&'f'));
+        assert_eq!(grid.get(3, 0), None);
+        assert_eq!(grid.get(0, 2), None);
+
+        let digits: Grid<Digit> = "12\n34\r\n".parse().unwrap();
+        assert_eq!(digits.get(1, 1), Some(&Digit(4)));
+
+        assert_eq!(
+            "12\n3x\n".parse::<Grid<Digit>>(),
+            Err(Error::Parse { line: Some(2), column: Some(2), field: None, reason: "`x` is not a digit".to_string() })
+        );
+
+        assert_eq!(
+            "12\n345\n".parse::<Grid<Digit>>(),
+            Err(Error::parse("row has 3 cells, expected 2").at_line(2))
+        );
+
+        assert_eq!("12\n\n34\n".parse::<Grid<Digit>>(), Err(Error::parse("row is empty").at_line(2)));
+        assert_eq!("\n12\n".parse::<Grid<Digit>>(), Err(Error::parse("row is empty").at_line(1)));
+
+        let empty: Grid<char> = "".parse().unwrap();
+        assert!(empty.is_empty());
+        assert_eq!(empty.get_wrapping(1, 1), None);
+    }
+
+    #[test]
+    fn test_new() {
+        assert!(Grid::new(2, 2, vec![1, 2, 3, 4]).is_ok());
+        assert_eq!(Grid::new(2, 2, vec![1, 2, 3]), Err(Error::validation("a 2x2 grid needs 4 cells, got 3")));
+    }
+
+    #[test]
+    fn test_from_rows_padded() {
+        let grid = Grid::from_rows_padded(vec![vec![1], vec![2, 3, 4], vec![5, 6]], 0);
+
+        assert_eq!((grid.width(), grid.height()), (3, 3));
+        assert_eq!(grid.rows().collect::<Vec<_>>(), vec![&[1, 0, 0][..], &[2, 3, 4], &[5, 6, 0]]);
+        assert!(Grid::<u8>::from_rows_padded(vec![], 0).is_empty());
+    }
+
+    #[test]
+    fn test_get_wrapping() {
+        let grid = grid();
+
+        assert_eq!(grid.get_wrapping(3, 0), Some(&'a'));
+        assert_eq!(grid.get_wrapping(-1, 0), Some(&'c'));
+        assert_eq!(grid.get_wrapping(4, 3), Some(&'e'));
+        assert_eq!(grid.get_wrapping(-4, -1), Some(&'f'));
+    }
+
+    #[test]
+    fn test_get_mut() {
+        let mut grid = grid();
+        *grid.get_mut(1, 1).unwrap() = 'x';
+
+        assert_eq!(grid.row(1), Some(&['d', 'x', 'f'][..]));
+        assert!(grid.get_mut(5, 5).is_none());
+    }
+
+    #[test]
+    fn test_neighbours() {
+        let grid = grid();
+        let neighbours = |x, y, n| grid.neighbours(x, y, n).map(|(_, _, &c)| c).collect::<String>();
+
+        assert_eq!(neighbours(0, 0, Neighbourhood::Four), "bd");
+        assert_eq!(neighbours(0, 0, Neighbourhood::Eight), "bde");
+        assert_eq!(neighbours(1, 1, Neighbourhood::Four), "bdf");
+        assert_eq!(neighbours(1, 1, Neighbourhood::Eight), "abcdf");
+        assert_eq!(grid.neighbours(2, 1, Neighbourhood::Four).map(|(x, y, _)| (x, y)).collect::<Vec<_>>(), vec![(2, 0), (1, 1)]);
+    }
+
+    #[test]
+    fn test_rows_and_columns() {
+        let grid = grid();
+
+        assert_eq!(grid.rows().map(|r| r.iter().collect::<String>()).collect::<Vec<_>>(), vec!["abc", "def"]);
+        assert_eq!(grid.columns().map(|c| c.collect::<String>()).collect::<Vec<_>>(), vec!["ad", "be", "cf"]);
+        assert_eq!(grid.column(3).count(), 0);
+        assert_eq!(grid.row(2), None);
+        assert_eq!(grid.iter().filter(|(x, _, _)| *x == 2).map(|(_, y, &c)| (y, c)).collect::<Vec<_>>(), vec![(0, 'c'), (1, 'f')]);
+    }
+}
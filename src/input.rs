@@ -1,26 +1,180 @@
 #![allow(dead_code)]
 
-use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, Read};
+use std::str::FromStr;
 
-pub fn read_nums(filename: &str) -> Vec<usize> {
-    let input = fs::read_to_string(filename)
-        .expect("err");
+use crate::error::{Error, Result};
 
-    input.split("\n")
-        .filter_map(|s| s.trim().parse::<usize>().ok())
-        .collect()
+/// How to deal with lines that can't be parsed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    /// Skip them.
+    Lenient,
+    /// Fail with an error pointing at the offending line.
+    Strict,
+}
+
+pub fn open(filename: &str) -> Result<BufReader<File>> {
+    File::open(filename)
+        .map(BufReader::new)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", filename, e)).into())
+}
+
+pub fn read_file(filename: &str) -> Result<String> {
+    read_to_string(open(filename)?)
+}
+
+pub fn read_to_string<R: Read>(mut reader: R) -> Result<String> {
+    let mut input = String::new();
+    reader.read_to_string(&mut input)?;
+    Ok(input)
+}
+
+/// Reads all non-blank lines, trimmed.
+pub fn read_lines<R: BufRead>(reader: R) -> Result<Vec<String>> {
+    let mut lines = vec![];
+
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+
+        if !line.is_empty() {
+            lines.push(line.to_string());
+        }
+    }
+
+    Ok(lines)
+}
+
+pub fn read_nums<R: BufRead>(reader: R, mode: Mode) -> Result<Vec<usize>> {
+    let mut nums = vec![];
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        match (line.parse::<usize>(), mode) {
+            (Ok(n), _) => nums.push(n),
+            (Err(_), Mode::Lenient) => (),
+            (Err(e), Mode::Strict) => {
+                return Err(Error::parse(format!("invalid number `{}`: {}", line, e)).at_line(i + 1));
+            }
+        }
+    }
+
+    Ok(nums)
 }
 
-pub fn read_lines(filename: &str) -> Vec<String> {
-    let input = fs::read_to_string(filename)
-        .expect("err");
+/// Reads groups of lines separated by one or more blank lines. The lines of
+/// a record are joined with `\n`.
+pub fn read_records<R: BufRead>(reader: R) -> Result<Vec<String>> {
+    let mut records = vec![];
+    let mut record: Vec<String> = vec![];
 
-    input.split("\n")
-        .filter_map(|s| s.trim().parse().ok())
-        .filter(|s| s != "")
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim_end();
+
+        if line.trim().is_empty() {
+            if !record.is_empty() {
+                records.push(record.join("\n"));
+                record.clear();
+            }
+        } else {
+            record.push(line.to_string());
+        }
+    }
+
+    if !record.is_empty() {
+        records.push(record.join("\n"));
+    }
+
+    Ok(records)
+}
+
+/// Parses every non-blank line of `input`, tagging errors with the line they
+/// occurred on.
+pub fn parse_lines<T: FromStr<Err = Error>>(input: &str) -> Result<Vec<T>> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| line.trim().parse().map_err(|e: Error| e.at_line(i + 1)))
         .collect()
 }
 
-pub fn read_file(filename: &str) -> String {
-    fs::read_to_string(filename).expect("err")
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_file_missing() {
+        let e = read_file("input/day0.txt").unwrap_err();
+        assert_eq!(e, Error::Io(io::ErrorKind::NotFound.into()));
+        assert!(e.to_string().contains("input/day0.txt"));
+    }
+
+    #[test]
+    fn test_read_lines() {
+        assert_eq!(
+            read_lines("  one\r\n\ntwo \n".as_bytes()),
+            Ok(vec!["one".to_string(), "two".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_read_nums() {
+        let input = "1721\n979\n\n36x\n299\n";
+
+        assert_eq!(read_nums(input.as_bytes(), Mode::Lenient), Ok(vec![1721, 979, 299]));
+        assert_eq!(
+            read_nums(input.as_bytes(), Mode::Strict),
+            Err(Error::Parse { line: Some(4), column: None, field: None, reason: "invalid number `36x`: invalid digit found in string".to_string() })
+        );
+    }
+
+    #[test]
+    fn test_read_records() {
+        let input = "abc\r\n\r\na\nb\n\n\n\nab\nac\n";
+
+        assert_eq!(
+            read_records(input.as_bytes()),
+            Ok(vec!["abc".to_string(), "a\nb".to_string(), "ab\nac".to_string()])
+        );
+
+        assert_eq!(read_records("\n\n".as_bytes()), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_read_from_file() {
+        assert_eq!(read_nums(open("input/day1.txt").unwrap(), Mode::Strict).map(|n| n.len()), Ok(200));
+        assert_eq!(read_records(open("input/day6.txt").unwrap()).map(|r| r.len()), Ok(491));
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Digit(u32);
+
+    impl FromStr for Digit {
+        type Err = Error;
+
+        fn from_str(s: &str) -> Result<Digit> {
+            s.parse().map(Digit).map_err(|_| Error::parse(format!("`{}` is not a digit", s)))
+        }
+    }
+
+    #[test]
+    fn test_parse_lines() {
+        assert_eq!(parse_lines("1\n\n 2\n"), Ok(vec![Digit(1), Digit(2)]));
+
+        assert_eq!(
+            parse_lines::<Digit>("1\n\nx\n"),
+            Err(Error::Parse { line: Some(3), column: None, field: None, reason: "`x` is not a digit".to_string() })
+        );
+    }
 }
@@ -0,0 +1,57 @@
+use std::fmt::Write;
+
+/// Quotes and escapes `s` as a JSON string.
+pub fn string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                write!(out, "\\u{:04x}", c as u32).unwrap();
+            }
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+    out
+}
+
+/// Builds a JSON object from already encoded values.
+pub fn object(fields: &[(&str, String)]) -> String {
+    let fields: Vec<String> = fields.iter().map(|(key, value)| format!("{}:{}", string(key), value)).collect();
+    format!("{{{}}}", fields.join(","))
+}
+
+/// Builds a JSON array from already encoded values.
+pub fn array(values: &[String]) -> String {
+    format!("[{}]", values.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_string() {
+        assert_eq!(string("abc"), r#""abc""#);
+        assert_eq!(string("a\"b\\c"), r#""a\"b\\c""#);
+        assert_eq!(string("tab\there\nnew\u{1}"), r#""tab\there\nnew\u0001""#);
+        assert_eq!(string("café 👍"), "\"café 👍\"");
+    }
+
+    #[test]
+    fn test_object() {
+        assert_eq!(
+            object(&[("line", 3.to_string()), ("tags", array(&[string("a"), string("b")]))]),
+            r#"{"line":3,"tags":["a","b"]}"#
+        );
+        assert_eq!(object(&[]), "{}");
+    }
+}
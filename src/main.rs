@@ -1,4 +1,9 @@
+mod cli;
+mod error;
+mod grid;
 mod input;
+mod json;
+mod solution;
 
 mod day1;
 mod day2;
@@ -8,6 +13,186 @@ mod day5;
 mod day6;
 mod day7;
 
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufWriter, Write};
+use std::process;
+
+use cli::Command;
+use day2::policy::PolicyRegistry;
+use day3::{Map, Position, Topology};
+use day4::schema::Schema;
+use day4::stats::{Format, Stats, BIRTH_BUCKET};
+use day4::Check;
+use solution::Part;
+
+fn read_input(path: &str) -> Result<String, String> {
+    let input = if path == "-" {
+        input::read_to_string(io::stdin().lock())
+    } else {
+        input::read_file(path)
+    };
+
+    input.map_err(|e| e.to_string())
+}
+
+fn run(day: u8, part: Option<Part>, input: Option<String>) -> Result<(), String> {
+    let puzzle = solution::find(day)
+        .ok_or_else(|| format!("there is no solution for day {}", day))?;
+
+    let path = input.unwrap_or_else(|| format!("input/day{}.txt", day));
+    let input = read_input(&path)?;
+
+    let parts = match part {
+        Some(p) => vec![p],
+        None => Part::ALL.to_vec(),
+    };
+
+    let run = puzzle.run(&input, &parts).map_err(|e| e.to_string())?;
+    println!("day {} parsed ({:.2?})", day, run.parse_elapsed);
+
+    for answer in run.answers.iter() {
+        println!("day {} part {}: {} ({:.2?})", day, answer.part, answer.value, answer.elapsed);
+    }
+
+    Ok(())
+}
+
+fn audit(specs: Vec<String>, input: Option<String>, report: Option<String>) -> Result<(), String> {
+    let registry = PolicyRegistry::new();
+    let specs = if specs.is_empty() {
+        vec!["occurrence".to_string(), "position".to_string()]
+    } else {
+        specs
+    };
+
+    let policies = specs
+        .iter()
+        .map(|spec| registry.create(spec))
+        .collect::<error::Result<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+
+    let path = input.unwrap_or_else(|| "input/day2.txt".to_string());
+    let reader: Box<dyn BufRead> = if path == "-" {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(input::open(&path).map_err(|e| e.to_string())?)
+    };
+
+    // invalid entries are listed as text on stdout, unless a JSON lines
+    // report is requested
+    let mut writer: Box<dyn Write> = match report.as_deref() {
+        None | Some("-") => Box::new(io::stdout().lock()),
+        Some(path) => {
+            let file = File::create(path).map_err(|e| format!("can't create `{}`: {}", path, e))?;
+            Box::new(BufWriter::new(file))
+        }
+    };
+
+    let summary = day2::audit::audit(reader, &policies, |invalid| {
+        if report.is_some() {
+            writeln!(writer, "{}", invalid.to_json())?;
+        } else {
+            for failure in invalid.failures.iter() {
+                writeln!(writer, "line {}: {}: {} ({})", invalid.line, failure.policy, failure.reason, invalid.entry)?;
+            }
+        }
+
+        Ok(())
+    }).map_err(|e| e.to_string())?;
+
+    writer.flush().map_err(|e| e.to_string())?;
+
+    let mut lines = vec![format!("checked {} passwords", summary.checked)];
+    if summary.unparsed > 0 {
+        lines.push(format!("{}: {} lines could not be parsed", day2::audit::PARSE, summary.unparsed));
+    }
+    for (policy, valid) in summary.valid.iter() {
+        lines.push(format!("{}: {} valid, {} invalid", policy, valid, summary.checked - valid));
+    }
+
+    // keep stdout clean for the JSON lines when the report goes there
+    for line in lines {
+        if report.as_deref() == Some("-") {
+            eprintln!("{}", line);
+        } else {
+            println!("{}", line);
+        }
+    }
+
+    Ok(())
+}
+
+fn trace(right: usize, down: usize, topology: Option<String>, input: Option<String>) -> Result<(), String> {
+    let path = input.unwrap_or_else(|| "input/day3.txt".to_string());
+    let topology = match topology {
+        Some(name) => name.parse::<Topology>().map_err(|e| e.to_string())?,
+        None => Topology::Cylinder,
+    };
+
+    let map = read_input(&path)?.parse::<Map>().map_err(|e| e.to_string())?.with_topology(topology);
+    let movement = Position { x: right, y: down };
+    let trace = map.trace(&movement);
+    let trees = trace.iter().filter(|(_, object)| *object == day3::Object::Tree).count();
+
+    print!("{}", map.render(&movement));
+    println!("visited {} squares, hit {} trees", trace.len(), trees);
+
+    Ok(())
+}
+
+fn passports(presence: bool, format: Option<String>, birth_bucket: Option<u16>, input: Option<String>) -> Result<(), String> {
+    let path = input.unwrap_or_else(|| "input/day4.txt".to_string());
+    let format = match format {
+        Some(name) => name.parse::<Format>().map_err(|e| e.to_string())?,
+        None => Format::Table,
+    };
+    let check = if presence { Check::Presence } else { Check::Strict };
+
+    let entries = input::read_records(read_input(&path)?.as_bytes()).map_err(|e| e.to_string())?;
+    let batch = day4::check_batch(&entries, Schema::passport(), check);
+
+    // the statistics are the output, rejected records go to stderr
+    for rejected in batch.rejected.iter() {
+        let reasons: Vec<&str> = rejected.errors.iter().map(|e| e.reason.as_str()).collect();
+        eprintln!("record {}: {}", rejected.record, reasons.join(", "));
+    }
+    eprintln!("{} valid, {} rejected", batch.valid.len(), batch.rejected.len());
+
+    let stats = Stats::collect(&batch.valid).with_birth_bucket(birth_bucket.unwrap_or(BIRTH_BUCKET));
+    print!("{}", stats.render(format));
+
+    Ok(())
+}
+
+fn normalize(drop_invalid: bool, input: Option<String>) -> Result<(), String> {
+    let path = input.unwrap_or_else(|| "input/day4.txt".to_string());
+    let entries = input::read_records(read_input(&path)?.as_bytes()).map_err(|e| e.to_string())?;
+    let drop_invalid = if drop_invalid { Some(Check::Strict) } else { None };
+
+    io::stdout()
+        .lock()
+        .write_all(day4::normalize(&entries, Schema::passport(), drop_invalid).as_bytes())
+        .map_err(|e| e.to_string())
+}
+
 fn main() {
-    println!("Vrolijke feestdagen!");
+    let command = Command::from_env().unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        eprintln!("{}", cli::USAGE);
+        process::exit(2);
+    });
+
+    let result = match command {
+        Command::Run { day, part, input } => run(day, part, input),
+        Command::Audit { policies, input, report } => audit(policies, input, report),
+        Command::Trace { right, down, topology, input } => trace(right, down, topology, input),
+        Command::Passports { presence, format, birth_bucket, input } => passports(presence, format, birth_bucket, input),
+        Command::Normalize { drop_invalid, input } => normalize(drop_invalid, input),
+    };
+
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
 }
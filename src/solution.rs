@@ -0,0 +1,126 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::error::Result;
+use crate::{day1, day2, day3, day4, day5, day6, day7};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Part {
+    One,
+    Two,
+}
+
+impl Part {
+    pub const ALL: [Part; 2] = [Part::One, Part::Two];
+}
+
+impl fmt::Display for Part {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Part::One => write!(f, "1"),
+            Part::Two => write!(f, "2"),
+        }
+    }
+}
+
+/// A single day of the puzzle calendar. The input is parsed once and then
+/// shared by both parts.
+pub trait Solution {
+    const DAY: u8;
+
+    type Input;
+    type Answer: fmt::Display;
+
+    fn parse(input: &str) -> Result<Self::Input>;
+    fn part_one(input: &Self::Input) -> Result<Self::Answer>;
+    fn part_two(input: &Self::Input) -> Result<Self::Answer>;
+}
+
+#[derive(Debug)]
+pub struct Answer {
+    pub part: Part,
+    pub value: String,
+    pub elapsed: Duration,
+}
+
+#[derive(Debug)]
+pub struct Run {
+    pub parse_elapsed: Duration,
+    pub answers: Vec<Answer>,
+}
+
+/// Object safe view on a `Solution`, so days with different input and answer
+/// types can live in the same registry.
+pub trait Puzzle: Sync {
+    fn day(&self) -> u8;
+    fn run(&self, input: &str, parts: &[Part]) -> Result<Run>;
+}
+
+impl<S: Solution + Sync> Puzzle for S {
+    fn day(&self) -> u8 {
+        S::DAY
+    }
+
+    fn run(&self, input: &str, parts: &[Part]) -> Result<Run> {
+        let start = Instant::now();
+        let parsed = S::parse(input)?;
+        let parse_elapsed = start.elapsed();
+
+        let mut answers = vec![];
+
+        for &part in parts.iter() {
+            let start = Instant::now();
+            let value = match part {
+                Part::One => S::part_one(&parsed)?,
+                Part::Two => S::part_two(&parsed)?,
+            };
+
+            answers.push(Answer { part, value: value.to_string(), elapsed: start.elapsed() });
+        }
+
+        Ok(Run { parse_elapsed, answers })
+    }
+}
+
+static SOLUTIONS: [&dyn Puzzle; 7] = [
+    &day1::Day1,
+    &day2::Day2,
+    &day3::Day3,
+    &day4::Day4,
+    &day5::Day5,
+    &day6::Day6,
+    &day7::Day7,
+];
+
+pub fn all() -> &'static [&'static dyn Puzzle] {
+    &SOLUTIONS
+}
+
+pub fn find(day: u8) -> Option<&'static dyn Puzzle> {
+    all().iter().find(|s| s.day() == day).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_is_ordered_by_day() {
+        let days: Vec<u8> = all().iter().map(|s| s.day()).collect();
+        assert_eq!(days, vec![1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn test_find() {
+        assert_eq!(find(3).map(|s| s.day()), Some(3));
+        assert!(find(25).is_none());
+    }
+
+    #[test]
+    fn test_run_parts() {
+        let run = find(6).unwrap().run("abc\n\na\nb\nc\n", &Part::ALL).unwrap();
+        let answers: Vec<(Part, &str)> = run.answers.iter().map(|a| (a.part, a.value.as_str())).collect();
+
+        assert_eq!(answers, vec![(Part::One, "6"), (Part::Two, "3")]);
+    }
+}